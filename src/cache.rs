@@ -1,9 +1,241 @@
+use chrono::Utc;
 use moka::future::Cache;
-use once_cell::sync::Lazy;
-use std::sync::Arc;
+use moka::ops::compute::Op;
+use once_cell::sync::{Lazy, OnceCell};
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::sync::broadcast;
 
-use crate::models::offices::OfficeAvailability;
+use crate::config::AppConfig;
+use crate::models::offices::{CachedOffice, OfficeAvailability};
 
-pub static OFFICE_CACHE: Lazy<Arc<Cache<String, OfficeAvailability>>> = Lazy::new(|| {
-    Arc::new(Cache::new(117)) // 117 dmvs in nc
-});
+/// Number of past events kept so reconnecting `/offices/stream` clients can catch up.
+pub const REPLAY_CAPACITY: usize = 512;
+
+/// 117 dmvs in nc, times the 13 services each can be scanned for.
+const OFFICE_CACHE_CAPACITY: u64 = 117 * 13;
+
+static OFFICE_CACHE: OnceCell<OfficeCache> = OnceCell::new();
+
+pub static OFFICE_FEED: Lazy<OfficeFeed> = Lazy::new(OfficeFeed::default);
+
+/// Latest scan of every office, keyed by (service title, office name) so scans for
+/// different services don't overwrite each other.
+pub struct OfficeCache {
+    entries: Cache<(String, String), OfficeAvailability>,
+    stale_after: Duration,
+}
+
+/// Sets the cache TTL and staleness from config. Must run before the first scan.
+pub fn init(config: &AppConfig) {
+    let cache = OfficeCache::new(
+        Duration::from_secs(config.office_cache_ttl_secs),
+        Duration::from_secs(config.office_stale_after_secs),
+    );
+    if OFFICE_CACHE.set(cache).is_err() {
+        tracing::warn!("Office cache was already initialized");
+    }
+}
+
+pub fn office_cache() -> &'static OfficeCache {
+    OFFICE_CACHE.get_or_init(|| {
+        let defaults = AppConfig::default();
+        OfficeCache::new(
+            Duration::from_secs(defaults.office_cache_ttl_secs),
+            Duration::from_secs(defaults.office_stale_after_secs),
+        )
+    })
+}
+
+impl OfficeCache {
+    fn new(ttl: Duration, stale_after: Duration) -> Self {
+        OfficeCache {
+            entries: Cache::builder()
+                .max_capacity(OFFICE_CACHE_CAPACITY)
+                .time_to_live(ttl)
+                .build(),
+            stale_after,
+        }
+    }
+
+    /// Stores a scraped office and notifies stream subscribers if its availability
+    /// is new or changed.
+    pub async fn record(&self, office: OfficeAvailability) {
+        let key = (office.service.clone(), office.office_name.clone());
+        // The entry stays locked while this runs, so concurrent scans of one office
+        // publish each change once and in the order they were stored.
+        self.entries
+            .entry(key)
+            .and_compute_with(|cached| async move {
+                if cached.is_none_or(|cached| cached.value().availability_differs(&office)) {
+                    OFFICE_FEED.publish(office.clone());
+                }
+                Op::Put(office)
+            })
+            .await;
+    }
+
+    /// Every cached office with its age.
+    pub fn snapshot(&self) -> Vec<CachedOffice> {
+        let now = Utc::now();
+        self.entries
+            .iter()
+            .map(|(_, office)| {
+                let age = now - office.scraped_at;
+                CachedOffice {
+                    age_secs: age.num_seconds(),
+                    stale: age.to_std().unwrap_or_default() > self.stale_after,
+                    office,
+                }
+            })
+            .collect()
+    }
+}
+
+/// A cached office that was added or changed.
+#[derive(Debug, Clone, Serialize)]
+pub struct OfficeEvent {
+    pub id: u64,
+    pub office: OfficeAvailability,
+}
+
+/// Broadcasts office changes to stream subscribers and remembers the most recent ones.
+pub struct OfficeFeed {
+    sender: broadcast::Sender<OfficeEvent>,
+    recent: Mutex<VecDeque<OfficeEvent>>,
+}
+
+impl Default for OfficeFeed {
+    fn default() -> Self {
+        OfficeFeed {
+            sender: broadcast::channel(REPLAY_CAPACITY).0,
+            recent: Mutex::new(VecDeque::with_capacity(REPLAY_CAPACITY)),
+        }
+    }
+}
+
+impl OfficeFeed {
+    pub fn subscribe(&self) -> broadcast::Receiver<OfficeEvent> {
+        self.sender.subscribe()
+    }
+
+    /// Events published after `last_id`, or `None` when some of them have already
+    /// been dropped from the replay buffer.
+    pub fn since(&self, last_id: u64) -> Option<Vec<OfficeEvent>> {
+        let recent = self.recent.lock().unwrap();
+        let latest = recent.back().map_or(0, |event| event.id);
+        if last_id > latest {
+            return None;
+        }
+        if let Some(oldest) = recent.front()
+            && oldest.id > last_id + 1
+        {
+            return None;
+        }
+        Some(
+            recent
+                .iter()
+                .filter(|event| event.id > last_id)
+                .cloned()
+                .collect(),
+        )
+    }
+
+    pub(crate) fn publish(&self, office: OfficeAvailability) {
+        let mut recent = self.recent.lock().unwrap();
+        let event = OfficeEvent {
+            id: recent.back().map_or(1, |event| event.id + 1),
+            office,
+        };
+        if recent.len() == REPLAY_CAPACITY {
+            recent.pop_front();
+        }
+        recent.push_back(event.clone());
+        // No subscribers is not an error.
+        let _ = self.sender.send(event);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ids(events: Option<Vec<OfficeEvent>>) -> Option<Vec<u64>> {
+        events.map(|events| events.iter().map(|event| event.id).collect())
+    }
+
+    #[tokio::test]
+    async fn only_changed_availability_is_published() {
+        let cache = OfficeCache::new(Duration::from_secs(60), Duration::from_secs(60));
+        let mut receiver = OFFICE_FEED.subscribe();
+        let office = OfficeAvailability::sample("Record Test Office");
+
+        cache.record(office.clone()).await;
+        // Rescanned later by another listener, but nothing changed.
+        cache
+            .record(OfficeAvailability {
+                source_listener: "other".to_string(),
+                scraped_at: Utc::now(),
+                ..office.clone()
+            })
+            .await;
+        cache
+            .record(OfficeAvailability {
+                is_reservable: false,
+                ..office.clone()
+            })
+            .await;
+
+        // Other tests may publish to the shared feed too.
+        let mut published = Vec::new();
+        while let Ok(event) = receiver.try_recv() {
+            if event.office.office_name == office.office_name {
+                published.push((event.office.is_reservable, event.office.source_listener));
+            }
+        }
+        assert_eq!(
+            published,
+            vec![(true, "test".to_string()), (false, "test".to_string())]
+        );
+        assert_eq!(cache.snapshot().len(), 1);
+    }
+
+    #[test]
+    fn since_replays_events_after_the_id() {
+        let feed = OfficeFeed::default();
+        assert_eq!(ids(feed.since(0)), Some(vec![]));
+
+        for name in ["Garner", "Cary", "Apex"] {
+            feed.publish(OfficeAvailability::sample(name));
+        }
+        assert_eq!(ids(feed.since(0)), Some(vec![1, 2, 3]));
+        assert_eq!(ids(feed.since(2)), Some(vec![3]));
+        assert_eq!(ids(feed.since(3)), Some(vec![]));
+    }
+
+    #[test]
+    fn since_detects_dropped_events() {
+        let feed = OfficeFeed::default();
+        for _ in 0..REPLAY_CAPACITY + 2 {
+            feed.publish(OfficeAvailability::sample("Garner"));
+        }
+
+        // Events 1 and 2 have been dropped; everything after 2 is still there.
+        assert_eq!(ids(feed.since(1)), None);
+        assert_eq!(
+            ids(feed.since(2)).map(|ids| (ids.len(), ids[0])),
+            Some((REPLAY_CAPACITY, 3))
+        );
+    }
+
+    #[test]
+    fn since_rejects_future_ids() {
+        let feed = OfficeFeed::default();
+        assert_eq!(ids(feed.since(1)), None);
+
+        feed.publish(OfficeAvailability::sample("Garner"));
+        assert_eq!(ids(feed.since(2)), None);
+    }
+}
@@ -0,0 +1,367 @@
+use serde::Deserialize;
+use std::error::Error;
+use std::fmt;
+use std::path::Path;
+use std::str::FromStr;
+
+use crate::scraping::selectors::SelectorProfile;
+
+/// File read when `CONFIG_FILE` is not set. It is optional.
+const DEFAULT_CONFIG_FILE: &str = "config.toml";
+
+/// Which `RequestStore` implementation to use.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StoreBackend {
+    Mongo,
+    Memory,
+}
+
+impl FromStr for StoreBackend {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "mongo" => Ok(StoreBackend::Mongo),
+            "memory" => Ok(StoreBackend::Memory),
+            other => Err(format!("expected 'mongo' or 'memory', got '{}'", other)),
+        }
+    }
+}
+
+/// Application settings, read from an optional TOML file and then overridden by
+/// environment variables of the same name in upper case.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct AppConfig {
+    pub host: String,
+    pub port: u16,
+    /// chromedriver endpoint the scraper connects to.
+    pub webdriver_url: String,
+    /// Portal URL and element IDs/classes, from the `[selectors]` table. Point
+    /// `base_url` at a staging mirror or mock portal; `PORTAL_URL` overrides it.
+    pub selectors: SelectorProfile,
+    /// Base URL of the proxy email service in `email_server/`.
+    pub email_proxy_url: String,
+    pub zipcode_csv_path: String,
+    /// reCAPTCHA site key of the NC DMV portal.
+    pub captcha_site_key: String,
+    pub twocaptcha_key: Option<String>,
+    /// Defaults to Mongo when `mongodb_uri` is set. Without it, the memory store
+    /// must be chosen explicitly since it loses every request on restart.
+    pub request_store: Option<StoreBackend>,
+    pub mongodb_uri: Option<String>,
+    /// Number of resumed listeners allowed to be starting Chrome at the same time.
+    pub resume_concurrency: usize,
+    /// How long shutdown waits for listeners to quit their Chrome sessions.
+    pub shutdown_timeout_secs: u64,
+    /// How long a scanned office stays in the cache without being rescanned.
+    pub office_cache_ttl_secs: u64,
+    /// Age after which a cached office is reported as stale.
+    pub office_stale_after_secs: u64,
+    /// Bearer token required by `/admin` routes. Admin routes reject every request when unset.
+    pub admin_token: Option<String>,
+    /// How often the portal's service tiles are compared with the catalog; 0 disables.
+    pub service_discovery_interval_secs: u64,
+    /// How often the selector canary walks the portal; 0 disables.
+    pub canary_interval_secs: u64,
+    /// Where screenshots and page source of failed scrapes are saved.
+    pub artifact_dir: String,
+    /// Captures kept per request, newest first; 0 disables capture.
+    pub artifact_max_per_request: usize,
+    /// Age after which captures are deleted.
+    pub artifact_retention_secs: u64,
+    /// Puts every request in dry-run mode: calendars are read, nothing is booked.
+    pub dry_run: bool,
+}
+
+impl Default for AppConfig {
+    fn default() -> Self {
+        AppConfig {
+            host: "0.0.0.0".to_string(),
+            port: 8675,
+            webdriver_url: "http://localhost:60103".to_string(),
+            selectors: SelectorProfile::default(),
+            email_proxy_url: "http://localhost:8000".to_string(),
+            zipcode_csv_path: "./zipcodetolatlong.csv".to_string(),
+            captcha_site_key: "6LegSQ0dAAAAALO2_3-EDnTRDc7AQLz6Jo1BFyct".to_string(),
+            twocaptcha_key: None,
+            request_store: None,
+            mongodb_uri: None,
+            resume_concurrency: 2,
+            shutdown_timeout_secs: 30,
+            office_cache_ttl_secs: 3600,
+            office_stale_after_secs: 300,
+            admin_token: None,
+            service_discovery_interval_secs: 86400,
+            canary_interval_secs: 3600,
+            artifact_dir: "./artifacts".to_string(),
+            artifact_max_per_request: 20,
+            artifact_retention_secs: 7 * 24 * 3600,
+            dry_run: false,
+        }
+    }
+}
+
+// Custom error type listing every missing or invalid setting.
+#[derive(Debug)]
+pub struct ConfigError {
+    pub problems: Vec<String>,
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Invalid configuration:")?;
+        for problem in &self.problems {
+            writeln!(f, "  - {}", problem)?;
+        }
+        Ok(())
+    }
+}
+
+impl Error for ConfigError {}
+
+impl AppConfig {
+    /// Loads `CONFIG_FILE` (or `config.toml` if present), applies environment
+    /// overrides and validates the result.
+    pub fn load() -> Result<Self, ConfigError> {
+        let mut problems = Vec::new();
+
+        let path = std::env::var("CONFIG_FILE").ok();
+        let mut config = match &path {
+            Some(path) => Self::from_file(path, &mut problems),
+            None if Path::new(DEFAULT_CONFIG_FILE).exists() => {
+                Self::from_file(DEFAULT_CONFIG_FILE, &mut problems)
+            }
+            None => AppConfig::default(),
+        };
+
+        config.apply_env(&mut problems);
+        config.validate(&mut problems);
+
+        if problems.is_empty() {
+            Ok(config)
+        } else {
+            Err(ConfigError { problems })
+        }
+    }
+
+    fn from_file(path: &str, problems: &mut Vec<String>) -> Self {
+        match std::fs::read_to_string(path) {
+            Ok(contents) => toml::from_str(&contents).unwrap_or_else(|e| {
+                problems.push(format!("{}: {}", path, e));
+                AppConfig::default()
+            }),
+            Err(e) => {
+                problems.push(format!("{}: {}", path, e));
+                AppConfig::default()
+            }
+        }
+    }
+
+    fn apply_env(&mut self, problems: &mut Vec<String>) {
+        fn var<T: FromStr>(name: &str, problems: &mut Vec<String>) -> Option<T>
+        where
+            T::Err: fmt::Display,
+        {
+            let value = std::env::var(name).ok()?;
+            match value.parse() {
+                Ok(parsed) => Some(parsed),
+                Err(e) => {
+                    problems.push(format!("{}='{}' is invalid: {}", name, value, e));
+                    None
+                }
+            }
+        }
+
+        if let Some(v) = var("HOST", problems) {
+            self.host = v;
+        }
+        if let Some(v) = var("PORT", problems) {
+            self.port = v;
+        }
+        if let Some(v) = var("WEBDRIVER_URL", problems) {
+            self.webdriver_url = v;
+        }
+        if let Some(v) = var("PORTAL_URL", problems) {
+            self.selectors.base_url = v;
+        }
+        if let Some(v) = var("EMAIL_PROXY_URL", problems) {
+            self.email_proxy_url = v;
+        }
+        if let Some(v) = var("ZIPCODE_CSV_PATH", problems) {
+            self.zipcode_csv_path = v;
+        }
+        if let Some(v) = var("CAPTCHA_SITE_KEY", problems) {
+            self.captcha_site_key = v;
+        }
+        if let Some(v) = var("TWOCAPTCHA_KEY", problems) {
+            self.twocaptcha_key = Some(v);
+        }
+        if let Some(v) = var("REQUEST_STORE", problems) {
+            self.request_store = Some(v);
+        }
+        if let Some(v) = var("MONGODB_URI", problems) {
+            self.mongodb_uri = Some(v);
+        }
+        if let Some(v) = var("RESUME_CONCURRENCY", problems) {
+            self.resume_concurrency = v;
+        }
+        if let Some(v) = var("SHUTDOWN_TIMEOUT_SECS", problems) {
+            self.shutdown_timeout_secs = v;
+        }
+        if let Some(v) = var("OFFICE_CACHE_TTL_SECS", problems) {
+            self.office_cache_ttl_secs = v;
+        }
+        if let Some(v) = var("OFFICE_STALE_AFTER_SECS", problems) {
+            self.office_stale_after_secs = v;
+        }
+        if let Some(v) = var("ADMIN_TOKEN", problems) {
+            self.admin_token = Some(v);
+        }
+        if let Some(v) = var("SERVICE_DISCOVERY_INTERVAL_SECS", problems) {
+            self.service_discovery_interval_secs = v;
+        }
+        if let Some(v) = var("CANARY_INTERVAL_SECS", problems) {
+            self.canary_interval_secs = v;
+        }
+        if let Some(v) = var("ARTIFACT_DIR", problems) {
+            self.artifact_dir = v;
+        }
+        if let Some(v) = var("ARTIFACT_MAX_PER_REQUEST", problems) {
+            self.artifact_max_per_request = v;
+        }
+        if let Some(v) = var("ARTIFACT_RETENTION_SECS", problems) {
+            self.artifact_retention_secs = v;
+        }
+        if let Some(v) = var("DRY_RUN", problems) {
+            self.dry_run = v;
+        }
+    }
+
+    fn validate(&mut self, problems: &mut Vec<String>) {
+        if self.port == 0 {
+            problems.push("port must not be 0".to_string());
+        }
+
+        for (name, value) in [
+            ("webdriver_url", &self.webdriver_url),
+            ("email_proxy_url", &self.email_proxy_url),
+        ] {
+            if let Err(e) = url::Url::parse(value) {
+                problems.push(format!("{}='{}' is not a valid URL: {}", name, value, e));
+            }
+        }
+
+        for error in self.selectors.validate() {
+            problems.push(format!("selectors.{} {}", error.field, error.message));
+        }
+
+        if !Path::new(&self.zipcode_csv_path).is_file() {
+            problems.push(format!(
+                "zipcode_csv_path='{}' does not exist",
+                self.zipcode_csv_path
+            ));
+        }
+
+        if self.captcha_site_key.is_empty() {
+            problems.push("captcha_site_key must not be empty".to_string());
+        }
+
+        if self.resume_concurrency == 0 {
+            problems.push("resume_concurrency must be at least 1".to_string());
+        }
+
+        if self.office_cache_ttl_secs == 0 {
+            problems.push("office_cache_ttl_secs must be at least 1".to_string());
+        }
+        if self.office_stale_after_secs > self.office_cache_ttl_secs {
+            problems
+                .push("office_stale_after_secs must not exceed office_cache_ttl_secs".to_string());
+        }
+
+        if self.request_store.is_none() && self.mongodb_uri.is_some() {
+            self.request_store = Some(StoreBackend::Mongo);
+        }
+        match self.request_store {
+            Some(StoreBackend::Mongo) if self.mongodb_uri.is_none() => {
+                problems.push("mongodb_uri is required when request_store is 'mongo'".to_string());
+            }
+            Some(StoreBackend::Memory) => {
+                tracing::warn!("request_store is 'memory'; requests are lost on restart");
+            }
+            Some(StoreBackend::Mongo) => {}
+            None => problems.push(
+                "mongodb_uri is not set; set it, or set request_store to 'memory' to keep requests in memory only"
+                    .to_string(),
+            ),
+        }
+
+        if self.admin_token.is_none() {
+            tracing::warn!("admin_token is not set; /admin routes are disabled");
+        }
+
+        if self.twocaptcha_key.is_none() {
+            tracing::warn!("twocaptcha_key is not set; listeners will fail when they try to book");
+        }
+    }
+
+    pub fn store_backend(&self) -> StoreBackend {
+        self.request_store.unwrap_or(StoreBackend::Memory)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn problems(config: &mut AppConfig) -> Vec<String> {
+        let mut problems = Vec::new();
+        config.validate(&mut problems);
+        problems
+    }
+
+    #[test]
+    fn request_store_must_be_chosen_without_mongodb_uri() {
+        let mut config = AppConfig::default();
+        assert!(
+            problems(&mut config)
+                .iter()
+                .any(|problem| problem.starts_with("mongodb_uri is not set"))
+        );
+
+        let mut memory = AppConfig {
+            request_store: Some(StoreBackend::Memory),
+            ..AppConfig::default()
+        };
+        assert!(
+            !problems(&mut memory)
+                .iter()
+                .any(|problem| problem.contains("mongodb_uri"))
+        );
+        assert_eq!(memory.store_backend(), StoreBackend::Memory);
+    }
+
+    #[test]
+    fn mongodb_uri_selects_mongo() {
+        let mut config = AppConfig {
+            mongodb_uri: Some("mongodb://localhost:27017".to_string()),
+            ..AppConfig::default()
+        };
+        assert!(
+            !problems(&mut config)
+                .iter()
+                .any(|problem| problem.contains("mongodb_uri"))
+        );
+        assert_eq!(config.store_backend(), StoreBackend::Mongo);
+
+        let mut missing_uri = AppConfig {
+            request_store: Some(StoreBackend::Mongo),
+            ..AppConfig::default()
+        };
+        assert!(
+            problems(&mut missing_uri)
+                .contains(&"mongodb_uri is required when request_store is 'mongo'".to_string())
+        );
+    }
+}
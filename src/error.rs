@@ -0,0 +1,156 @@
+use actix_web::http::StatusCode;
+use actix_web::{HttpResponse, ResponseError};
+use serde_json::json;
+use std::fmt;
+
+use crate::models::appointment::FieldError;
+use crate::scraping::error::ScraperError;
+
+/// External systems a request can fail on.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Upstream {
+    /// skiptheline.ncdot.gov
+    Portal,
+    Captcha,
+    EmailProxy,
+    Database,
+}
+
+/// Errors returned by every route, rendered as
+/// `{"error": {"code": ..., "message": ..., "fields": [...]}}`.
+#[derive(Debug)]
+pub enum ApiError {
+    /// The request body or parameters could not be parsed.
+    BadRequest(String),
+    /// One or more fields failed validation.
+    Validation(Vec<FieldError>),
+    /// Missing or wrong admin token.
+    Unauthorized,
+    NotFound(String),
+    /// An external system failed or was unreachable.
+    Upstream(Upstream, String),
+    Internal(String),
+}
+
+impl ApiError {
+    pub fn not_found(what: impl fmt::Display) -> Self {
+        ApiError::NotFound(format!("{} not found", what))
+    }
+
+    pub fn database(error: impl fmt::Debug) -> Self {
+        ApiError::Upstream(Upstream::Database, format!("{:?}", error))
+    }
+
+    pub fn internal(error: impl fmt::Debug) -> Self {
+        ApiError::Internal(format!("{:?}", error))
+    }
+
+    /// Machine-readable code the frontend switches on.
+    pub fn code(&self) -> &'static str {
+        match self {
+            ApiError::BadRequest(_) => "bad_request",
+            ApiError::Validation(_) => "validation_failed",
+            ApiError::Unauthorized => "unauthorized",
+            ApiError::NotFound(_) => "not_found",
+            ApiError::Upstream(Upstream::Portal, _) => "portal_unavailable",
+            ApiError::Upstream(Upstream::Captcha, _) => "captcha_failed",
+            ApiError::Upstream(Upstream::EmailProxy, _) => "email_proxy_unavailable",
+            ApiError::Upstream(Upstream::Database, _) => "database_unavailable",
+            ApiError::Internal(_) => "internal_error",
+        }
+    }
+
+    /// Message safe to show to users; upstream and internal details are only logged.
+    fn public_message(&self) -> String {
+        match self {
+            ApiError::BadRequest(message) | ApiError::NotFound(message) => message.clone(),
+            ApiError::Validation(fields) => {
+                format!("{} field(s) failed validation", fields.len())
+            }
+            ApiError::Unauthorized => "A valid admin token is required".to_string(),
+            ApiError::Upstream(Upstream::Portal, _) => {
+                "The NC DMV portal could not be reached".to_string()
+            }
+            ApiError::Upstream(Upstream::Captcha, _) => {
+                "The captcha could not be solved".to_string()
+            }
+            ApiError::Upstream(Upstream::EmailProxy, _) => {
+                "The email proxy could not be reached".to_string()
+            }
+            ApiError::Upstream(Upstream::Database, _) => {
+                "Request storage is unavailable".to_string()
+            }
+            ApiError::Internal(_) => "An internal error occurred".to_string(),
+        }
+    }
+}
+
+impl fmt::Display for ApiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ApiError::BadRequest(message) | ApiError::NotFound(message) => {
+                write!(f, "{}", message)
+            }
+            ApiError::Validation(fields) => {
+                write!(f, "Validation failed on {} field(s)", fields.len())
+            }
+            ApiError::Unauthorized => write!(f, "Unauthorized"),
+            ApiError::Upstream(upstream, message) => write!(f, "{:?} error: {}", upstream, message),
+            ApiError::Internal(message) => write!(f, "Internal error: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for ApiError {}
+
+impl From<ScraperError> for ApiError {
+    fn from(error: ScraperError) -> Self {
+        let message = error.to_string();
+        match error {
+            ScraperError::Captcha(_) => ApiError::Upstream(Upstream::Captcha, message),
+            ScraperError::EmailProxy(_) => ApiError::Upstream(Upstream::EmailProxy, message),
+            ScraperError::Config(_) => ApiError::Internal(message),
+            _ => ApiError::Upstream(Upstream::Portal, message),
+        }
+    }
+}
+
+impl ResponseError for ApiError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            ApiError::BadRequest(_) | ApiError::Validation(_) => StatusCode::BAD_REQUEST,
+            ApiError::Unauthorized => StatusCode::UNAUTHORIZED,
+            ApiError::NotFound(_) => StatusCode::NOT_FOUND,
+            ApiError::Upstream(..) => StatusCode::BAD_GATEWAY,
+            ApiError::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        if self.status_code().is_server_error() {
+            tracing::error!("{}", self);
+        }
+
+        let fields = match self {
+            ApiError::Validation(fields) => fields.as_slice(),
+            _ => &[],
+        };
+
+        HttpResponse::build(self.status_code()).json(json!({
+            "error": {
+                "code": self.code(),
+                "message": self.public_message(),
+                "fields": fields,
+            }
+        }))
+    }
+}
+
+/// Renders actix extractor failures (malformed JSON, bad path or query segments)
+/// as `ApiError::BadRequest` so they share the JSON error shape.
+pub fn extractor_error(
+    error: impl fmt::Display,
+    _req: &actix_web::HttpRequest,
+) -> actix_web::Error {
+    ApiError::BadRequest(error.to_string()).into()
+}
@@ -0,0 +1,149 @@
+use once_cell::sync::Lazy;
+use std::fmt::Write;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::time::Instant;
+
+use crate::config::AppConfig;
+use crate::scraping::canary::{self, CanaryReport};
+use crate::scraping::error::ScraperResult;
+
+static CANARY_REPORT: Lazy<Mutex<Option<CanaryReport>>> = Lazy::new(|| Mutex::new(None));
+
+/// Serializes canary runs so an admin request doesn't start a second Chrome.
+static CANARY_RUNNING: Lazy<tokio::sync::Mutex<()>> = Lazy::new(|| tokio::sync::Mutex::new(()));
+
+static RUNS_PASSED: AtomicU64 = AtomicU64::new(0);
+static RUNS_FAILED: AtomicU64 = AtomicU64::new(0);
+
+pub fn report() -> Option<CanaryReport> {
+    CANARY_REPORT.lock().unwrap().clone()
+}
+
+/// Walks the portal with the active selector profile and alerts when a step fails.
+pub async fn run_canary(config: &AppConfig) -> ScraperResult<CanaryReport> {
+    let _running = CANARY_RUNNING.lock().await;
+
+    let report = canary::run_canary(config).await?;
+    match &report.failure {
+        None => {
+            RUNS_PASSED.fetch_add(1, Ordering::Relaxed);
+        }
+        Some(failure) => {
+            RUNS_FAILED.fetch_add(1, Ordering::Relaxed);
+            tracing::error!(
+                "Selector canary failed at {:?}: {} (unresolved: {:?})",
+                failure.step,
+                failure.error,
+                failure.missing
+            );
+        }
+    }
+
+    *CANARY_REPORT.lock().unwrap() = Some(report.clone());
+    Ok(report)
+}
+
+/// Runs the canary every `canary_interval_secs`, the first time one period after
+/// startup so restarts don't each launch a Chrome session.
+pub async fn run_periodically(config: Arc<AppConfig>) {
+    if config.canary_interval_secs == 0 {
+        return;
+    }
+
+    let period = Duration::from_secs(config.canary_interval_secs);
+    let mut interval = tokio::time::interval_at(Instant::now() + period, period);
+    loop {
+        interval.tick().await;
+        if let Err(e) = run_canary(&config).await {
+            tracing::error!("Selector canary could not run: {}", e);
+        }
+    }
+}
+
+/// Canary results in the Prometheus text format.
+pub fn metrics() -> String {
+    render_metrics(
+        report().as_ref(),
+        RUNS_PASSED.load(Ordering::Relaxed),
+        RUNS_FAILED.load(Ordering::Relaxed),
+    )
+}
+
+fn render_metrics(report: Option<&CanaryReport>, passed: u64, failed: u64) -> String {
+    let mut out = String::new();
+    let _ = writeln!(
+        out,
+        "# HELP dmv_selector_canary_runs_total Selector canary runs by result."
+    );
+    let _ = writeln!(out, "# TYPE dmv_selector_canary_runs_total counter");
+    let _ = writeln!(
+        out,
+        "dmv_selector_canary_runs_total{{result=\"pass\"}} {}",
+        passed
+    );
+    let _ = writeln!(
+        out,
+        "dmv_selector_canary_runs_total{{result=\"fail\"}} {}",
+        failed
+    );
+
+    // Without a run yet there is nothing to report as up or down.
+    let Some(report) = report else {
+        return out;
+    };
+    let _ = writeln!(
+        out,
+        "# HELP dmv_selector_canary_up Whether the last selector canary run passed."
+    );
+    let _ = writeln!(out, "# TYPE dmv_selector_canary_up gauge");
+    let _ = writeln!(out, "dmv_selector_canary_up {}", u8::from(report.passed));
+    let _ = writeln!(
+        out,
+        "# HELP dmv_selector_canary_last_run_timestamp_seconds When the last selector canary run finished."
+    );
+    let _ = writeln!(
+        out,
+        "# TYPE dmv_selector_canary_last_run_timestamp_seconds gauge"
+    );
+    let _ = writeln!(
+        out,
+        "dmv_selector_canary_last_run_timestamp_seconds {}",
+        report.checked_at.timestamp()
+    );
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scraping::canary::{CanaryFailure, CanaryStep};
+    use crate::scraping::page::PageState;
+    use chrono::{TimeZone, Utc};
+
+    #[test]
+    fn metrics_report_the_last_run() {
+        assert!(!render_metrics(None, 0, 0).contains("dmv_selector_canary_up"));
+
+        let report = CanaryReport {
+            checked_at: Utc.timestamp_opt(1_800_000_000, 0).unwrap(),
+            passed: false,
+            checked: vec![CanaryStep::Landing],
+            failure: Some(CanaryFailure {
+                step: CanaryStep::ServiceSelection,
+                error: "item_child_class matched nothing".to_string(),
+                missing: vec!["item_child_class"],
+                page: PageState::Unknown,
+                url: None,
+                snapshot: String::new(),
+            }),
+        };
+        let metrics = render_metrics(Some(&report), 4, 1);
+
+        assert!(metrics.contains("dmv_selector_canary_runs_total{result=\"pass\"} 4\n"));
+        assert!(metrics.contains("dmv_selector_canary_runs_total{result=\"fail\"} 1\n"));
+        assert!(metrics.contains("dmv_selector_canary_up 0\n"));
+        assert!(metrics.contains("dmv_selector_canary_last_run_timestamp_seconds 1800000000\n"));
+    }
+}
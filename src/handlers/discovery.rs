@@ -0,0 +1,50 @@
+use once_cell::sync::Lazy;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::time::Instant;
+
+use crate::config::AppConfig;
+use crate::scraping::discovery::{DiscoveryReport, discover_services};
+use crate::scraping::error::ScraperResult;
+
+static DISCOVERY_REPORT: Lazy<Mutex<Option<DiscoveryReport>>> = Lazy::new(|| Mutex::new(None));
+
+/// Serializes discovery runs so an admin request doesn't start a second Chrome.
+static DISCOVERY_RUNNING: Lazy<tokio::sync::Mutex<()>> = Lazy::new(|| tokio::sync::Mutex::new(()));
+
+pub fn report() -> Option<DiscoveryReport> {
+    DISCOVERY_REPORT.lock().unwrap().clone()
+}
+
+/// Compares the portal's service tiles with the catalog and alerts on any difference.
+pub async fn run_discovery(config: &AppConfig) -> ScraperResult<DiscoveryReport> {
+    let _running = DISCOVERY_RUNNING.lock().await;
+
+    let report = discover_services(config).await?;
+    if !report.diff.is_empty() {
+        tracing::error!(
+            "Portal services differ from the catalog: {}",
+            serde_json::to_string(&report.diff).unwrap_or_default()
+        );
+    }
+
+    *DISCOVERY_REPORT.lock().unwrap() = Some(report.clone());
+    Ok(report)
+}
+
+/// Runs discovery every `service_discovery_interval_secs`, the first time one
+/// period after startup. `POST /admin/services/discovery` runs it sooner.
+pub async fn discover_periodically(config: Arc<AppConfig>) {
+    if config.service_discovery_interval_secs == 0 {
+        return;
+    }
+
+    let period = Duration::from_secs(config.service_discovery_interval_secs);
+    let mut interval = tokio::time::interval_at(Instant::now() + period, period);
+    loop {
+        interval.tick().await;
+        if let Err(e) = run_discovery(&config).await {
+            tracing::error!("Service discovery failed: {}", e);
+        }
+    }
+}
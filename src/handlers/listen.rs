@@ -1,43 +1,136 @@
-use crate::cache::OFFICE_CACHE;
+use crate::cache::office_cache;
+use crate::config::AppConfig;
+use crate::models::appointment::{AppointmentRequest, RequestStatus};
 use crate::models::dmvservice::DMVService;
+use crate::registry::{LISTENERS, ListenerPhase, ListenerStatus, ListenerTracker};
 use crate::scraping::scraper::NCDMVScraper;
+use crate::store::{RequestStore, transition_request};
 use anyhow::Result;
 use std::sync::Arc;
 use tokio::task;
 
+/// Spawns a listener for `request` and returns the tracker it reports through.
 pub async fn listen(
-    zipcode: String,
-    max_distance: u16,
-    name: String,
-    phone_number: String,
-    email: String,
+    store: Arc<dyn RequestStore>,
+    config: Arc<AppConfig>,
+    request: &AppointmentRequest,
     service_type: DMVService,
-    dates: Vec<String>,
-) -> Result<()> {
-    task::spawn(async move {
-        match NCDMVScraper::new(zipcode.clone(), max_distance, name, phone_number, email).await {
+) -> Result<ListenerTracker> {
+    let tracker = ListenerTracker::new(request.request_id.clone());
+    let task_tracker = tracker.clone();
+
+    persist_lifecycle(store, &tracker);
+
+    let AppointmentRequest {
+        zipcode,
+        max_distance,
+        name,
+        phone_number,
+        email,
+        dates,
+        dry_run,
+        ..
+    } = request.clone();
+
+    let handle = task::spawn(async move {
+        let tracker = task_tracker;
+        match NCDMVScraper::new(
+            config,
+            zipcode.clone(),
+            max_distance,
+            name,
+            phone_number,
+            email,
+        )
+        .await
+        {
             Ok(scraper) => {
-                let scraper = Arc::new(scraper);
+                let scraper = Arc::new(scraper.with_dry_run(dry_run));
                 let mut receiver = scraper
                     .clone()
-                    .start_appointment_stream(1, service_type, dates)
+                    .start_appointment_stream(1, service_type, dates, tracker.clone())
                     .await;
 
                 while let Some(offices) = receiver.recv().await {
                     for office in offices {
-                        OFFICE_CACHE
-                            .insert(office.office_name.clone(), office)
-                            .await;
+                        office_cache().record(office).await;
                     }
                 }
 
                 tracing::warn!("Receiver closed for {}", zipcode);
+                if !tracker.snapshot().phase.is_final() {
+                    tracker.set_phase(ListenerPhase::Stopped);
+                }
             }
             Err(e) => {
                 tracing::error!("Failed to start scraper for {}: {:?}", zipcode, e);
+                tracker.fail(format!("{:?}", e));
+            }
+        }
+    });
+
+    LISTENERS.register(tracker.clone(), handle);
+
+    Ok(tracker)
+}
+
+/// Maps a listener phase onto the persisted request lifecycle.
+fn lifecycle_status(phase: ListenerPhase) -> Option<RequestStatus> {
+    match phase {
+        ListenerPhase::Scanning => Some(RequestStatus::Scanning),
+        ListenerPhase::Booking => Some(RequestStatus::Booking),
+        ListenerPhase::Booked => Some(RequestStatus::Booked),
+        ListenerPhase::Failed => Some(RequestStatus::Failed),
+        ListenerPhase::Expired => Some(RequestStatus::Expired),
+        _ => None,
+    }
+}
+
+/// Writes the listener's progress to the stored request until it stops.
+fn persist_lifecycle(store: Arc<dyn RequestStore>, tracker: &ListenerTracker) {
+    let mut phases = tracker.subscribe();
+    let tracker = tracker.clone();
+
+    task::spawn(async move {
+        while let Some(phase) = phases.recv().await {
+            if let Some(status) = lifecycle_status(phase) {
+                let snapshot = tracker.snapshot();
+                let reason = (status == RequestStatus::Failed)
+                    .then_some(snapshot.last_error)
+                    .flatten();
+                if let Err(e) =
+                    transition_request(store.as_ref(), &snapshot.request_id, status, reason).await
+                {
+                    tracing::error!(
+                        "Failed to record {:?} for request {}: {:?}",
+                        status,
+                        snapshot.request_id,
+                        e
+                    );
+                }
+            }
+
+            if phase.is_final() {
+                break;
             }
         }
     });
+}
+
+pub fn status(request_id: &str) -> Option<ListenerStatus> {
+    LISTENERS.status(request_id)
+}
+
+pub async fn cancel(store: &dyn RequestStore, request_id: &str) -> Option<ListenerStatus> {
+    let status = LISTENERS.stop(request_id).await?;
+
+    if !matches!(
+        status.phase,
+        ListenerPhase::Booked | ListenerPhase::Expired | ListenerPhase::Failed
+    ) && let Err(e) = transition_request(store, request_id, RequestStatus::Cancelled, None).await
+    {
+        tracing::error!("Failed to record cancellation of {}: {:?}", request_id, e);
+    }
 
-    Ok(())
+    Some(status)
 }
@@ -1,2 +1,6 @@
+pub mod canary;
+pub mod discovery;
 pub mod listen;
 pub mod offices;
+pub mod resume;
+pub mod services;
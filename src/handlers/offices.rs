@@ -1,8 +1,665 @@
-use crate::cache::OFFICE_CACHE;
-use crate::models::offices::OfficeAvailability;
+use crate::cache::{OFFICE_FEED, OfficeEvent, OfficeFeed, office_cache};
+use crate::error::ApiError;
+use crate::models::appointment::FieldError;
+use crate::models::dmvservice::DMVService;
+use crate::models::offices::{CachedOffice, OfficeAvailability};
+use crate::models::zipcode::{ZipCodeData, distance_between};
+use actix_web::web::Bytes;
+use chrono::{NaiveDate, Utc};
+use futures_util::Stream;
+use serde::Deserialize;
+use serde_json::json;
+use std::collections::VecDeque;
+use std::convert::Infallible;
+use std::time::Duration;
+use tokio::sync::broadcast::{self, error::RecvError};
+use tokio::time::{Interval, interval};
 
-pub async fn get_available_appointments()
--> Result<Vec<OfficeAvailability>, Box<dyn std::error::Error>> {
-    let offices: Vec<_> = OFFICE_CACHE.iter().map(|entry| entry.1.clone()).collect();
-    Ok(offices)
+/// How often `/offices/stream` sends a heartbeat so proxies keep the connection open.
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(15);
+
+/// How long browsers wait before reconnecting a dropped stream, in milliseconds.
+const RECONNECT_DELAY_MS: u64 = 5000;
+
+/// Largest page `/offices/all` returns.
+const MAX_PER_PAGE: usize = 200;
+
+/// Office filters shared by `GET /offices/all` and `GET /offices/stream`.
+/// Every filter is optional.
+#[derive(Debug, Default, Deserialize)]
+pub struct OfficeFilter {
+    /// Service slug or title, e.g. `renewal`.
+    pub service: Option<String>,
+    /// Origin ZIP code; requires `radius`.
+    pub zip: Option<String>,
+    /// Miles from `zip`.
+    pub radius: Option<f64>,
+    #[serde(default)]
+    pub reservable_only: bool,
+    /// Only offices with an available date on or after this day.
+    pub available_from: Option<NaiveDate>,
+    /// Only offices with an available date on or before this day.
+    pub available_until: Option<NaiveDate>,
+    /// Case-insensitive substring of the office name.
+    pub name: Option<String>,
+}
+
+impl OfficeFilter {
+    pub fn validate(&self, zipcodes: &ZipCodeData) -> Result<(), Vec<FieldError>> {
+        let mut errors = Vec::new();
+
+        if let Some(service) = &self.service
+            && let Err(e) = service.parse::<DMVService>()
+        {
+            errors.push(FieldError::new("service", e.to_string()));
+        }
+
+        match (&self.zip, self.radius) {
+            (Some(zip), Some(radius)) => {
+                if !zipcodes.contains_key(zip) {
+                    errors.push(FieldError::new(
+                        "zip",
+                        "is not a known North Carolina ZIP code",
+                    ));
+                }
+                if radius <= 0.0 {
+                    errors.push(FieldError::new("radius", "must be greater than 0"));
+                }
+            }
+            (Some(_), None) => errors.push(FieldError::new("radius", "is required with zip")),
+            (None, Some(_)) => errors.push(FieldError::new("zip", "is required with radius")),
+            (None, None) => {}
+        }
+
+        if let (Some(from), Some(until)) = (self.available_from, self.available_until)
+            && from > until
+        {
+            errors.push(FieldError::new(
+                "available_until",
+                "must not be before available_from",
+            ));
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    fn matches(&self, office: &OfficeAvailability, zipcodes: &ZipCodeData) -> bool {
+        if self.reservable_only && !office.is_reservable {
+            return false;
+        }
+        if let Some(service) = &self.service
+            && service
+                .parse::<DMVService>()
+                .is_ok_and(|service| service.title() != office.service)
+        {
+            return false;
+        }
+        if let Some(name) = &self.name
+            && !office
+                .office_name
+                .to_lowercase()
+                .contains(&name.to_lowercase())
+        {
+            return false;
+        }
+        if (self.available_from.is_some() || self.available_until.is_some())
+            && !office.available_dates.iter().any(|date| {
+                self.available_from.is_none_or(|from| *date >= from)
+                    && self.available_until.is_none_or(|until| *date <= until)
+            })
+        {
+            return false;
+        }
+        if let (Some(zip), Some(radius)) = (&self.zip, self.radius) {
+            return distance_between(zipcodes, zip, &office.zip_code)
+                .is_some_and(|distance| distance <= radius);
+        }
+        true
+    }
+
+    /// Miles from the origin ZIP when one is given, otherwise from the scanning
+    /// listener's ZIP.
+    fn distance(&self, office: &OfficeAvailability, zipcodes: &ZipCodeData) -> f64 {
+        self.zip
+            .as_ref()
+            .and_then(|zip| distance_between(zipcodes, zip, &office.zip_code))
+            .unwrap_or(office.distance as f64)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OfficeSort {
+    Distance,
+    EarliestDate,
+}
+
+/// Sorting and pagination of `GET /offices/all`.
+#[derive(Debug, Deserialize)]
+pub struct OfficePage {
+    pub sort: Option<OfficeSort>,
+    /// 1-based.
+    #[serde(default = "default_page")]
+    pub page: usize,
+    #[serde(default = "default_per_page")]
+    pub per_page: usize,
+}
+
+fn default_page() -> usize {
+    1
+}
+
+fn default_per_page() -> usize {
+    50
+}
+
+impl OfficePage {
+    pub fn validate(&self) -> Result<(), Vec<FieldError>> {
+        let mut errors = Vec::new();
+        if self.page == 0 {
+            errors.push(FieldError::new("page", "must be at least 1"));
+        }
+        if self.per_page == 0 || self.per_page > MAX_PER_PAGE {
+            errors.push(FieldError::new(
+                "per_page",
+                format!("must be between 1 and {}", MAX_PER_PAGE),
+            ));
+        }
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+/// One page of cached offices matching `filter`, and the total number of matches.
+pub async fn get_available_appointments(
+    filter: &OfficeFilter,
+    page: &OfficePage,
+    zipcodes: &ZipCodeData,
+) -> Result<(Vec<CachedOffice>, usize), ApiError> {
+    Ok(select_page(
+        office_cache().snapshot(),
+        filter,
+        page,
+        zipcodes,
+    ))
+}
+
+/// Filters, sorts and paginates `offices`.
+fn select_page(
+    offices: Vec<CachedOffice>,
+    filter: &OfficeFilter,
+    page: &OfficePage,
+    zipcodes: &ZipCodeData,
+) -> (Vec<CachedOffice>, usize) {
+    let mut offices: Vec<_> = offices
+        .into_iter()
+        .filter(|cached| filter.matches(&cached.office, zipcodes))
+        .collect();
+
+    match page.sort {
+        Some(OfficeSort::Distance) => offices.sort_by(|a, b| {
+            filter
+                .distance(&a.office, zipcodes)
+                .total_cmp(&filter.distance(&b.office, zipcodes))
+        }),
+        Some(OfficeSort::EarliestDate) => offices.sort_by_key(|cached| {
+            // Offices without dates sort last.
+            let earliest = cached.office.available_dates.iter().min().copied();
+            (earliest.is_none(), earliest)
+        }),
+        None => offices.sort_by(|a, b| {
+            (&a.office.service, &a.office.office_name)
+                .cmp(&(&b.office.service, &b.office.office_name))
+        }),
+    }
+
+    let total = offices.len();
+    let offices = offices
+        .into_iter()
+        .skip((page.page - 1) * page.per_page)
+        .take(page.per_page)
+        .collect();
+
+    (offices, total)
+}
+
+struct StreamState {
+    receiver: broadcast::Receiver<OfficeEvent>,
+    pending: VecDeque<Bytes>,
+    heartbeat: Interval,
+    last_sent: u64,
+    filter: OfficeFilter,
+    zipcodes: ZipCodeData,
+}
+
+fn office_frame(event: &OfficeEvent) -> Bytes {
+    let data = serde_json::to_string(event).unwrap_or_default();
+    Bytes::from(format!(
+        "id: {}\nevent: office\ndata: {}\n\n",
+        event.id, data
+    ))
+}
+
+/// Tells the client it missed events and should reload `/offices/all`.
+fn reset_frame() -> Bytes {
+    Bytes::from("event: reset\ndata: {}\n\n")
+}
+
+fn heartbeat_frame() -> Bytes {
+    Bytes::from(format!(
+        "event: heartbeat\ndata: {}\n\n",
+        json!({ "at": Utc::now() })
+    ))
+}
+
+/// Server-sent events for every office that is added to or changes in the cache.
+///
+/// Clients reconnecting with `Last-Event-ID` first receive the events they missed,
+/// or a `reset` event when those are no longer buffered.
+pub fn office_stream(
+    filter: OfficeFilter,
+    last_event_id: Option<u64>,
+    zipcodes: ZipCodeData,
+) -> impl Stream<Item = Result<Bytes, Infallible>> {
+    // Subscribe before reading the backlog so nothing published in between is lost.
+    let receiver = OFFICE_FEED.subscribe();
+    feed_stream(&OFFICE_FEED, receiver, filter, last_event_id, zipcodes)
+}
+
+/// Streams `feed` from a `receiver` subscribed before the backlog is read. Events
+/// both replayed and received are only sent once.
+fn feed_stream(
+    feed: &OfficeFeed,
+    receiver: broadcast::Receiver<OfficeEvent>,
+    filter: OfficeFilter,
+    last_event_id: Option<u64>,
+    zipcodes: ZipCodeData,
+) -> impl Stream<Item = Result<Bytes, Infallible>> + use<> {
+    let mut pending = VecDeque::from([Bytes::from(format!("retry: {}\n\n", RECONNECT_DELAY_MS))]);
+    let mut last_sent = 0;
+    if let Some(last_id) = last_event_id {
+        match feed.since(last_id) {
+            Some(missed) => {
+                last_sent = missed.last().map_or(last_id, |event| event.id);
+                pending.extend(
+                    missed
+                        .iter()
+                        .filter(|event| filter.matches(&event.office, &zipcodes))
+                        .map(office_frame),
+                );
+            }
+            None => pending.push_back(reset_frame()),
+        }
+    }
+
+    let state = StreamState {
+        receiver,
+        pending,
+        heartbeat: interval(HEARTBEAT_INTERVAL),
+        last_sent,
+        filter,
+        zipcodes,
+    };
+
+    futures_util::stream::unfold(state, |mut state| async move {
+        loop {
+            if let Some(frame) = state.pending.pop_front() {
+                return Some((Ok(frame), state));
+            }
+
+            tokio::select! {
+                received = state.receiver.recv() => match received {
+                    Ok(event) if event.id > state.last_sent => {
+                        state.last_sent = event.id;
+                        if state.filter.matches(&event.office, &state.zipcodes) {
+                            state.pending.push_back(office_frame(&event));
+                        }
+                    }
+                    Ok(_) => {}
+                    Err(RecvError::Lagged(_)) => state.pending.push_back(reset_frame()),
+                    Err(RecvError::Closed) => return None,
+                },
+                _ = state.heartbeat.tick() => state.pending.push_back(heartbeat_frame()),
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cache::REPLAY_CAPACITY;
+    use crate::scraping::fixtures::date;
+    use futures_util::StreamExt;
+    use std::collections::HashMap;
+    use std::sync::Arc;
+
+    /// Downtown Raleigh, Garner (about 7 miles away) and Charlotte (about 130).
+    fn zipcodes() -> ZipCodeData {
+        Arc::new(HashMap::from([
+            ("27601".to_string(), (35.7727, -78.6387)),
+            ("27529".to_string(), (35.6680, -78.5800)),
+            ("28202".to_string(), (35.2270, -80.8430)),
+        ]))
+    }
+
+    fn cached(name: &str, zip_code: &str, distance: u16, dates: &[&str]) -> CachedOffice {
+        CachedOffice {
+            office: OfficeAvailability {
+                zip_code: zip_code.to_string(),
+                distance,
+                available_dates: dates.iter().map(|d| date(d)).collect(),
+                ..OfficeAvailability::sample(name)
+            },
+            age_secs: 0,
+            stale: false,
+        }
+    }
+
+    fn window(from: Option<&str>, until: Option<&str>) -> OfficeFilter {
+        OfficeFilter {
+            available_from: from.map(date),
+            available_until: until.map(date),
+            ..OfficeFilter::default()
+        }
+    }
+
+    fn page(sort: Option<OfficeSort>, page: usize, per_page: usize) -> OfficePage {
+        OfficePage {
+            sort,
+            page,
+            per_page,
+        }
+    }
+
+    fn names(offices: &[CachedOffice]) -> Vec<&str> {
+        offices
+            .iter()
+            .map(|cached| cached.office.office_name.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn date_window_bounds_are_inclusive() {
+        let office = cached("Garner", "27529", 7, &["2026-12-01", "2026-12-10"]).office;
+        let no_dates = cached("Cary", "27529", 7, &[]).office;
+        let zipcodes = zipcodes();
+
+        for (from, until, expected) in [
+            (None, None, true),
+            (Some("2026-12-10"), None, true),
+            (Some("2026-12-11"), None, false),
+            (None, Some("2026-12-01"), true),
+            (None, Some("2026-11-30"), false),
+            (Some("2026-12-01"), Some("2026-12-01"), true),
+            // Dates on both sides of the window, none inside it.
+            (Some("2026-12-02"), Some("2026-12-09"), false),
+        ] {
+            assert_eq!(
+                window(from, until).matches(&office, &zipcodes),
+                expected,
+                "{:?}..{:?}",
+                from,
+                until
+            );
+        }
+        assert!(window(None, None).matches(&no_dates, &zipcodes));
+        assert!(!window(Some("2026-12-01"), None).matches(&no_dates, &zipcodes));
+    }
+
+    #[test]
+    fn radius_is_measured_from_the_origin_zip() {
+        let zipcodes = zipcodes();
+        let filter = |radius| OfficeFilter {
+            zip: Some("27601".to_string()),
+            radius: Some(radius),
+            ..OfficeFilter::default()
+        };
+        let garner = cached("Garner", "27529", 7, &[]).office;
+        let charlotte = cached("Charlotte", "28202", 130, &[]).office;
+        let unknown = cached("Nowhere", "00000", 0, &[]).office;
+
+        assert!(filter(10.0).matches(&garner, &zipcodes));
+        assert!(!filter(5.0).matches(&garner, &zipcodes));
+        assert!(!filter(10.0).matches(&charlotte, &zipcodes));
+        assert!(filter(200.0).matches(&charlotte, &zipcodes));
+        assert!(!filter(200.0).matches(&unknown, &zipcodes));
+    }
+
+    #[test]
+    fn offices_are_sorted() {
+        let zipcodes = zipcodes();
+        let offices = || {
+            vec![
+                cached("Charlotte", "28202", 130, &["2026-12-01"]),
+                cached("Apex", "27529", 3, &[]),
+                cached("Garner", "27529", 7, &["2026-12-15", "2026-12-03"]),
+            ]
+        };
+
+        let (sorted, _) = select_page(
+            offices(),
+            &OfficeFilter::default(),
+            &page(None, 1, 50),
+            &zipcodes,
+        );
+        assert_eq!(names(&sorted), vec!["Apex", "Charlotte", "Garner"]);
+
+        // Offices without dates sort last.
+        let (sorted, _) = select_page(
+            offices(),
+            &OfficeFilter::default(),
+            &page(Some(OfficeSort::EarliestDate), 1, 50),
+            &zipcodes,
+        );
+        assert_eq!(names(&sorted), vec!["Charlotte", "Garner", "Apex"]);
+
+        // The scanning listener's distance without an origin ZIP.
+        let (sorted, _) = select_page(
+            offices(),
+            &OfficeFilter::default(),
+            &page(Some(OfficeSort::Distance), 1, 50),
+            &zipcodes,
+        );
+        assert_eq!(names(&sorted), vec!["Apex", "Garner", "Charlotte"]);
+
+        // The distance from the origin ZIP when one is given.
+        let from_charlotte = OfficeFilter {
+            zip: Some("28202".to_string()),
+            radius: Some(500.0),
+            ..OfficeFilter::default()
+        };
+        let (sorted, _) = select_page(
+            offices(),
+            &from_charlotte,
+            &page(Some(OfficeSort::Distance), 1, 50),
+            &zipcodes,
+        );
+        assert_eq!(sorted[0].office.office_name, "Charlotte");
+    }
+
+    #[test]
+    fn pages_split_the_matches() {
+        let zipcodes = zipcodes();
+        let offices: Vec<_> = ["Apex", "Cary", "Garner", "Raleigh", "Wake Forest"]
+            .into_iter()
+            .map(|name| cached(name, "27529", 7, &[]))
+            .collect();
+
+        let (second, total) = select_page(
+            offices.clone(),
+            &OfficeFilter::default(),
+            &page(None, 2, 2),
+            &zipcodes,
+        );
+        assert_eq!(total, 5);
+        assert_eq!(names(&second), vec!["Garner", "Raleigh"]);
+
+        let (past_the_end, total) =
+            select_page(offices, &named("ar"), &page(None, 2, 2), &zipcodes);
+        assert_eq!(total, 2);
+        assert!(past_the_end.is_empty());
+    }
+
+    #[test]
+    fn page_and_per_page_are_validated() {
+        assert!(page(None, 1, 1).validate().is_ok());
+        assert!(page(None, 1, MAX_PER_PAGE).validate().is_ok());
+
+        for (invalid, field) in [
+            (page(None, 0, 50), "page"),
+            (page(None, 1, 0), "per_page"),
+            (page(None, 1, MAX_PER_PAGE + 1), "per_page"),
+        ] {
+            let errors = invalid.validate().unwrap_err();
+            assert_eq!(errors.len(), 1);
+            assert_eq!(errors[0].field, field);
+        }
+    }
+
+    #[test]
+    fn filters_are_validated() {
+        let zipcodes = zipcodes();
+        let errors = |filter: OfficeFilter| -> Vec<&'static str> {
+            filter
+                .validate(&zipcodes)
+                .err()
+                .unwrap_or_default()
+                .iter()
+                .map(|e| e.field)
+                .collect()
+        };
+
+        assert!(errors(window(Some("2026-12-01"), Some("2026-12-01"))).is_empty());
+        assert_eq!(
+            errors(window(Some("2026-12-02"), Some("2026-12-01"))),
+            vec!["available_until"]
+        );
+        assert_eq!(
+            errors(OfficeFilter {
+                zip: Some("27601".to_string()),
+                ..OfficeFilter::default()
+            }),
+            vec!["radius"]
+        );
+        assert_eq!(
+            errors(OfficeFilter {
+                zip: Some("00000".to_string()),
+                radius: Some(0.0),
+                ..OfficeFilter::default()
+            }),
+            vec!["zip", "radius"]
+        );
+    }
+
+    fn named(name: &str) -> OfficeFilter {
+        OfficeFilter {
+            name: Some(name.to_string()),
+            ..OfficeFilter::default()
+        }
+    }
+
+    fn stream(
+        feed: &OfficeFeed,
+        receiver: broadcast::Receiver<OfficeEvent>,
+        filter: OfficeFilter,
+        last_event_id: Option<u64>,
+    ) -> impl Stream<Item = Result<Bytes, Infallible>> + Unpin + use<> {
+        Box::pin(feed_stream(
+            feed,
+            receiver,
+            filter,
+            last_event_id,
+            Arc::new(HashMap::new()),
+        ))
+    }
+
+    /// The next frame that isn't a heartbeat.
+    async fn next_frame(
+        stream: &mut (impl Stream<Item = Result<Bytes, Infallible>> + Unpin),
+    ) -> String {
+        loop {
+            let frame = stream.next().await.unwrap().unwrap();
+            let frame = String::from_utf8(frame.to_vec()).unwrap();
+            if !frame.starts_with("event: heartbeat") {
+                return frame;
+            }
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn missed_events_are_replayed_once() {
+        let feed = OfficeFeed::default();
+        feed.publish(OfficeAvailability::sample("Garner"));
+        let receiver = feed.subscribe();
+        // Published after subscribing, so also waiting in the receiver.
+        feed.publish(OfficeAvailability::sample("Cary"));
+        feed.publish(OfficeAvailability::sample("Garner"));
+
+        let mut stream = stream(&feed, receiver, named("garner"), Some(1));
+        feed.publish(OfficeAvailability::sample("Garner"));
+
+        assert!(next_frame(&mut stream).await.starts_with("retry: "));
+        assert!(next_frame(&mut stream).await.starts_with("id: 3\n"));
+        assert!(next_frame(&mut stream).await.starts_with("id: 4\n"));
+        assert!(
+            tokio::time::timeout(Duration::from_secs(60), next_frame(&mut stream))
+                .await
+                .is_err()
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn new_events_are_filtered() {
+        let feed = OfficeFeed::default();
+        let mut stream = stream(&feed, feed.subscribe(), named("cary"), None);
+        feed.publish(OfficeAvailability::sample("Garner"));
+        feed.publish(OfficeAvailability::sample("Cary"));
+
+        assert!(next_frame(&mut stream).await.starts_with("retry: "));
+        let frame = next_frame(&mut stream).await;
+        assert!(frame.starts_with("id: 2\nevent: office\n"), "{}", frame);
+        assert!(frame.contains("\"office_name\":\"Cary\""));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn unbuffered_ids_reset_the_client() {
+        let feed = OfficeFeed::default();
+        for _ in 0..REPLAY_CAPACITY + 2 {
+            feed.publish(OfficeAvailability::sample("Garner"));
+        }
+
+        // Event 2 was dropped from the buffer; event 600 doesn't exist yet.
+        for last_id in [1, 600] {
+            let mut stream = stream(
+                &feed,
+                feed.subscribe(),
+                OfficeFilter::default(),
+                Some(last_id),
+            );
+            assert!(next_frame(&mut stream).await.starts_with("retry: "));
+            assert!(next_frame(&mut stream).await.starts_with("event: reset\n"));
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn lagging_clients_are_reset() {
+        let feed = OfficeFeed::default();
+        let mut stream = stream(&feed, feed.subscribe(), OfficeFilter::default(), None);
+        for _ in 0..REPLAY_CAPACITY + 1 {
+            feed.publish(OfficeAvailability::sample("Garner"));
+        }
+
+        assert!(next_frame(&mut stream).await.starts_with("retry: "));
+        assert!(next_frame(&mut stream).await.starts_with("event: reset\n"));
+        // The receiver resumes at the oldest event still in the channel.
+        assert!(next_frame(&mut stream).await.starts_with("id: 2\n"));
+    }
 }
@@ -0,0 +1,170 @@
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use std::sync::Mutex;
+
+use crate::config::AppConfig;
+use crate::handlers::listen::listen;
+use crate::models::appointment::RequestStatus;
+use crate::models::dmvservice::DMVService;
+use crate::registry::{LISTENERS, ListenerPhase};
+use crate::store::{RequestStore, transition_request};
+use chrono::Local;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Semaphore;
+
+/// How long a resumed listener may take to reach the office list before it is
+/// stopped and its request marked failed.
+const STARTUP_TIMEOUT: Duration = Duration::from_secs(120);
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ResumeFailure {
+    pub request_id: String,
+    pub reason: String,
+}
+
+/// Which persisted requests were resumed at startup and which failed.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ResumeReport {
+    pub resumed: Vec<String>,
+    pub failed: Vec<ResumeFailure>,
+}
+
+static RESUME_REPORT: Lazy<Mutex<ResumeReport>> = Lazy::new(|| Mutex::new(ResumeReport::default()));
+
+pub fn report() -> ResumeReport {
+    RESUME_REPORT.lock().unwrap().clone()
+}
+
+async fn record_failure(store: &dyn RequestStore, request_id: String, reason: String) {
+    tracing::error!("Failed to resume request {}: {}", request_id, reason);
+    if let Err(e) = transition_request(
+        store,
+        &request_id,
+        RequestStatus::Failed,
+        Some(reason.clone()),
+    )
+    .await
+    {
+        tracing::error!("Failed to record failure of {}: {:?}", request_id, e);
+    }
+    RESUME_REPORT
+        .lock()
+        .unwrap()
+        .failed
+        .push(ResumeFailure { request_id, reason });
+}
+
+/// Re-spawns listeners for every persisted request that has not reached a terminal
+/// status. Requests whose dates have all passed are marked expired instead.
+///
+/// At most `resume_concurrency` listeners are started at once; the next one starts
+/// when a previous one reaches the office list or fails.
+pub async fn resume_requests(store: Arc<dyn RequestStore>, config: Arc<AppConfig>) {
+    let concurrency = config.resume_concurrency;
+
+    let resumable = [
+        RequestStatus::Pending,
+        RequestStatus::Scanning,
+        RequestStatus::Booking,
+    ];
+    let requests = match store.list(&resumable).await {
+        Ok(requests) => requests,
+        Err(e) => {
+            tracing::error!("Failed to load persisted requests: {:?}", e);
+            return;
+        }
+    };
+
+    let today = Local::now().date_naive();
+    let (expired, requests): (Vec<_>, Vec<_>) = requests
+        .into_iter()
+        .partition(|request| request.is_expired(today));
+
+    for request in expired {
+        if let Err(e) = transition_request(
+            store.as_ref(),
+            &request.request_id,
+            RequestStatus::Expired,
+            None,
+        )
+        .await
+        {
+            tracing::error!("Failed to expire request {}: {:?}", request.request_id, e);
+        }
+    }
+
+    tracing::info!(
+        "Resuming {} persisted requests, {} at a time",
+        requests.len(),
+        concurrency
+    );
+
+    let semaphore = Arc::new(Semaphore::new(concurrency));
+
+    for request in requests {
+        let permit = semaphore.clone().acquire_owned().await.unwrap();
+
+        let service_type = match request.service_title.parse::<DMVService>() {
+            Ok(service) => service,
+            Err(e) => {
+                record_failure(store.as_ref(), request.request_id, e.to_string()).await;
+                continue;
+            }
+        };
+
+        let tracker = match listen(store.clone(), config.clone(), &request, service_type).await {
+            Ok(tracker) => tracker,
+            Err(e) => {
+                record_failure(store.as_ref(), request.request_id, format!("{:?}", e)).await;
+                continue;
+            }
+        };
+
+        let request_id = request.request_id;
+        let store = store.clone();
+        tokio::spawn(async move {
+            let started = tokio::time::Instant::now();
+            loop {
+                let status = tracker.snapshot();
+
+                // A DELETE or shutdown during startup is neither a resume nor a failure.
+                if tracker.is_cancelled()
+                    || matches!(
+                        status.phase,
+                        ListenerPhase::Stopping | ListenerPhase::Stopped
+                    )
+                {
+                    tracing::info!("Request {} was stopped while resuming", request_id);
+                    break;
+                }
+
+                match status.phase {
+                    ListenerPhase::Starting | ListenerPhase::Navigating => {
+                        if started.elapsed() >= STARTUP_TIMEOUT {
+                            LISTENERS.stop(&request_id).await;
+                            let reason = format!(
+                                "listener did not reach the office list within {}s",
+                                STARTUP_TIMEOUT.as_secs()
+                            );
+                            record_failure(store.as_ref(), request_id, reason).await;
+                            break;
+                        }
+                        tokio::time::sleep(Duration::from_secs(1)).await;
+                    }
+                    ListenerPhase::Failed => {
+                        let reason = status.last_error.unwrap_or_default();
+                        record_failure(store.as_ref(), request_id, reason).await;
+                        break;
+                    }
+                    _ => {
+                        tracing::info!("Resumed request {}", request_id);
+                        RESUME_REPORT.lock().unwrap().resumed.push(request_id);
+                        break;
+                    }
+                }
+            }
+            drop(permit);
+        });
+    }
+}
@@ -0,0 +1,7 @@
+use crate::models::dmvservice::{DMVService, ServiceDefinition};
+
+pub fn list_services() -> Vec<&'static ServiceDefinition> {
+    DMVService::all()
+        .map(|service| service.definition())
+        .collect()
+}
@@ -1,19 +1,67 @@
 mod cache;
+mod config;
+mod error;
 mod handlers;
 mod models;
+mod registry;
 mod routes;
 mod scraping;
+mod shutdown;
+mod store;
 
-use actix_web::{App, HttpServer};
+use actix_web::{App, HttpServer, web};
 use dotenv::dotenv;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::config::AppConfig;
+use crate::models::zipcode;
 
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
     tracing_subscriber::fmt::init();
-    dotenv().expect("Failed to load .env file");
+    // A .env file is optional; settings can also come from the environment or config.toml.
+    dotenv().ok();
+
+    let config = match AppConfig::load() {
+        Ok(config) => Arc::new(config),
+        Err(e) => {
+            tracing::error!("{}", e);
+            std::process::exit(1);
+        }
+    };
 
-    HttpServer::new(|| App::new().configure(routes::init))
-        .bind(("0.0.0.0", 80))?
-        .run()
+    cache::init(&config);
+    let zipcode_data = web::Data::new(zipcode::load_zipcode_data(&config.zipcode_csv_path));
+
+    let request_store = store::from_config(&config)
         .await
+        .map_err(std::io::Error::other)?;
+    tokio::spawn(handlers::resume::resume_requests(
+        request_store.clone(),
+        config.clone(),
+    ));
+    tokio::spawn(handlers::discovery::discover_periodically(config.clone()));
+    tokio::spawn(handlers::canary::run_periodically(config.clone()));
+
+    let bind_address = (config.host.clone(), config.port);
+    let shutdown_timeout = Duration::from_secs(config.shutdown_timeout_secs);
+    let store_data: web::Data<dyn store::RequestStore> = web::Data::from(request_store.clone());
+    let config = web::Data::from(config);
+
+    tracing::info!("Listening on {}:{}", bind_address.0, bind_address.1);
+
+    HttpServer::new(move || {
+        App::new()
+            .app_data(zipcode_data.clone())
+            .app_data(store_data.clone())
+            .app_data(config.clone())
+            .configure(routes::init)
+    })
+    .bind(bind_address)?
+    .run()
+    .await?;
+
+    shutdown::shutdown(request_store, shutdown_timeout).await;
+    Ok(())
 }
@@ -0,0 +1,622 @@
+use chrono::{DateTime, Local, NaiveDate, Utc};
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::fmt;
+use uuid::Uuid;
+
+use crate::models::dmvservice::DMVService;
+use crate::models::zipcode::ZipCodeData;
+
+static EMAIL_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r"^[^@\s]+@[^@\s]+\.[^@\s]+$").unwrap());
+
+/// The appointment request document to be stored in MongoDB.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AppointmentRequest {
+    /// Older documents were written before request IDs existed.
+    #[serde(default)]
+    pub request_id: String,
+    pub zipcode: String,
+    pub max_distance: u16,
+    pub name: String,
+    pub phone_number: String,
+    pub email: String,
+    pub service_title: String,
+    pub selector: String,
+    pub dates: Vec<String>,
+    /// Documents written before the lifecycle existed are treated as pending.
+    #[serde(default)]
+    pub status: RequestStatus,
+    #[serde(default)]
+    pub status_history: Vec<StatusTransition>,
+    /// Why the request ended up `Failed`, if it did.
+    #[serde(default)]
+    pub failure_reason: Option<String>,
+    /// Report matching dates without ever booking.
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+/// Lifecycle of a persisted appointment request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RequestStatus {
+    /// Stored, but no listener has reached the office list yet.
+    #[default]
+    Pending,
+    /// A listener is sweeping office calendars.
+    Scanning,
+    /// A listener is filling in the portal's booking form.
+    Booking,
+    /// The booking form was submitted.
+    Booked,
+    /// The listener gave up; see `failure_reason`.
+    Failed,
+    /// Every requested date has passed.
+    Expired,
+    /// The user stopped the request.
+    Cancelled,
+}
+
+/// A single recorded status change.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StatusTransition {
+    pub from: Option<RequestStatus>,
+    pub to: RequestStatus,
+    pub at: DateTime<Utc>,
+}
+
+// Custom error type for a transition the lifecycle does not allow.
+#[derive(Debug)]
+pub struct InvalidTransitionError {
+    pub from: RequestStatus,
+    pub to: RequestStatus,
+}
+
+impl fmt::Display for InvalidTransitionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Cannot move request from {:?} to {:?}",
+            self.from, self.to
+        )
+    }
+}
+
+impl Error for InvalidTransitionError {}
+
+impl RequestStatus {
+    /// Whether the lifecycle allows moving from `self` to `next`.
+    pub fn can_transition_to(self, next: RequestStatus) -> bool {
+        use RequestStatus::*;
+        matches!(
+            (self, next),
+            (Pending, Scanning | Failed | Expired | Cancelled)
+                // Listeners stopped for a restart go back to pending so they are resumed.
+                | (Scanning, Pending | Booking | Failed | Expired | Cancelled)
+                // A booking attempt that backs out returns to scanning.
+                | (Booking, Pending | Scanning | Booked | Failed | Expired | Cancelled)
+        )
+    }
+}
+
+impl AppointmentRequest {
+    /// Moves the request to `to`, recording the transition.
+    pub fn transition(
+        &mut self,
+        to: RequestStatus,
+        failure_reason: Option<String>,
+    ) -> Result<(), InvalidTransitionError> {
+        if !self.status.can_transition_to(to) {
+            return Err(InvalidTransitionError {
+                from: self.status,
+                to,
+            });
+        }
+
+        self.status_history.push(StatusTransition {
+            from: Some(self.status),
+            to,
+            at: Utc::now(),
+        });
+        self.status = to;
+        if to == RequestStatus::Failed {
+            self.failure_reason = failure_reason;
+        }
+        Ok(())
+    }
+
+    /// The last date the user will accept, after which the request is expired.
+    pub fn latest_date(&self) -> Option<NaiveDate> {
+        self.dates
+            .iter()
+            .filter_map(|d| NaiveDate::parse_from_str(d, "%Y-%m-%d").ok())
+            .max()
+    }
+
+    pub fn is_expired(&self, today: NaiveDate) -> bool {
+        self.latest_date().is_none_or(|latest| latest < today)
+    }
+}
+
+#[cfg(test)]
+impl AppointmentRequest {
+    /// A pending request for a first-time license, with no recorded history.
+    pub fn sample(request_id: &str) -> Self {
+        AppointmentRequest {
+            request_id: request_id.to_string(),
+            zipcode: "27610".to_string(),
+            max_distance: 25,
+            name: "Test_User".to_string(),
+            phone_number: "9195550100".to_string(),
+            email: "user@example.com".to_string(),
+            service_title: "Driver License - First Time".to_string(),
+            selector: "New driver over 18".to_string(),
+            dates: vec!["2030-01-01".to_string()],
+            status: RequestStatus::Pending,
+            status_history: Vec::new(),
+            failure_reason: None,
+            dry_run: false,
+        }
+    }
+}
+
+/// JSON body accepted by `POST /listen/requests`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct NewAppointmentRequest {
+    pub zipcode: String,
+    pub max_distance: u16,
+    /// First and last name separated by an underscore, as typed into the portal form.
+    pub name: String,
+    pub phone_number: String,
+    pub email: String,
+    /// Service slug or title from `GET /services`.
+    pub service_title: String,
+    /// Acceptable appointment dates in `YYYY-MM-DD` format.
+    pub dates: Vec<String>,
+    /// Only report offices with matching dates; never fill in the booking form.
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+/// A single field that failed validation.
+#[derive(Debug, Clone, Serialize)]
+pub struct FieldError {
+    pub field: &'static str,
+    pub message: String,
+}
+
+impl FieldError {
+    pub fn new(field: &'static str, message: impl Into<String>) -> Self {
+        FieldError {
+            field,
+            message: message.into(),
+        }
+    }
+}
+
+impl NewAppointmentRequest {
+    /// Validates every field and, if all pass, builds the stored request with a fresh ID.
+    ///
+    /// All failures are collected so the caller can report them in one response.
+    pub fn validate(
+        self,
+        zipcodes: &ZipCodeData,
+    ) -> Result<(AppointmentRequest, DMVService), Vec<FieldError>> {
+        let mut errors = Vec::new();
+
+        let zipcode = self.zipcode.trim().to_string();
+        if zipcode.len() != 5 || !zipcode.chars().all(|c| c.is_ascii_digit()) {
+            errors.push(FieldError::new("zipcode", "must be a 5 digit ZIP code"));
+        } else if !zipcodes.contains_key(&zipcode) {
+            errors.push(FieldError::new(
+                "zipcode",
+                "is not a known North Carolina ZIP code",
+            ));
+        }
+
+        if self.max_distance == 0 {
+            errors.push(FieldError::new("max_distance", "must be greater than 0"));
+        }
+
+        let name = self.name.trim().to_string();
+        if name.is_empty() {
+            errors.push(FieldError::new("name", "must not be empty"));
+        } else if name.len() > 100 {
+            errors.push(FieldError::new("name", "must be at most 100 characters"));
+        } else if !name
+            .split_once('_')
+            .is_some_and(|(first, last)| !first.trim().is_empty() && !last.trim().is_empty())
+        {
+            errors.push(FieldError::new(
+                "name",
+                "must be a first and last name separated by an underscore",
+            ));
+        }
+
+        let phone_number: String = self
+            .phone_number
+            .chars()
+            .filter(|c| c.is_ascii_digit())
+            .collect();
+        if phone_number.len() != 10 {
+            errors.push(FieldError::new(
+                "phone_number",
+                "must contain exactly 10 digits",
+            ));
+        }
+
+        let email = self.email.trim().to_string();
+        if !EMAIL_REGEX.is_match(&email) {
+            errors.push(FieldError::new("email", "must be a valid email address"));
+        }
+
+        let service_type = match self.service_title.parse::<DMVService>() {
+            Ok(service) => Some(service),
+            Err(e) => {
+                errors.push(FieldError::new("service_title", e.to_string()));
+                None
+            }
+        };
+
+        let today = Local::now().date_naive();
+        let dates: Vec<String> = self.dates.iter().map(|d| d.trim().to_string()).collect();
+        if dates.is_empty() {
+            errors.push(FieldError::new("dates", "must contain at least one date"));
+        }
+        for date in &dates {
+            match NaiveDate::parse_from_str(date, "%Y-%m-%d") {
+                Ok(parsed) if parsed < today => {
+                    errors.push(FieldError::new(
+                        "dates",
+                        format!("'{}' is in the past", date),
+                    ));
+                }
+                Ok(_) => {}
+                Err(_) => {
+                    errors.push(FieldError::new(
+                        "dates",
+                        format!("'{}' is not a YYYY-MM-DD date", date),
+                    ));
+                }
+            }
+        }
+
+        match service_type {
+            Some(service_type) if errors.is_empty() => Ok((
+                AppointmentRequest {
+                    request_id: Uuid::new_v4().to_string(),
+                    zipcode,
+                    max_distance: self.max_distance,
+                    name,
+                    phone_number,
+                    email,
+                    service_title: service_type.title().to_string(),
+                    selector: service_type.selector().to_string(),
+                    dates,
+                    status: RequestStatus::Pending,
+                    status_history: vec![StatusTransition {
+                        from: None,
+                        to: RequestStatus::Pending,
+                        at: Utc::now(),
+                    }],
+                    failure_reason: None,
+                    dry_run: self.dry_run,
+                },
+                service_type,
+            )),
+            _ => Err(errors),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use RequestStatus::*;
+    use std::collections::HashMap;
+    use std::sync::Arc;
+
+    const ALL: [RequestStatus; 7] = [
+        Pending, Scanning, Booking, Booked, Failed, Expired, Cancelled,
+    ];
+
+    fn request(status: RequestStatus) -> AppointmentRequest {
+        AppointmentRequest {
+            status,
+            ..AppointmentRequest::sample("req-1")
+        }
+    }
+
+    fn new_request() -> NewAppointmentRequest {
+        NewAppointmentRequest {
+            zipcode: "27610".to_string(),
+            max_distance: 25,
+            name: "Test_User".to_string(),
+            phone_number: "(919) 555-0100".to_string(),
+            email: "user@example.com".to_string(),
+            service_title: "renewal".to_string(),
+            dates: vec![in_days(30)],
+            dry_run: false,
+        }
+    }
+
+    fn in_days(days: i64) -> String {
+        (Local::now().date_naive() + chrono::Duration::days(days))
+            .format("%Y-%m-%d")
+            .to_string()
+    }
+
+    fn zipcodes() -> ZipCodeData {
+        Arc::new(HashMap::from([("27610".to_string(), (35.7449, -78.5486))]))
+    }
+
+    /// Fields rejected for `request`, in the order they were checked.
+    fn rejected(request: NewAppointmentRequest) -> Vec<&'static str> {
+        match request.validate(&zipcodes()) {
+            Ok(_) => Vec::new(),
+            Err(errors) => errors.iter().map(|e| e.field).collect(),
+        }
+    }
+
+    #[test]
+    fn valid_requests_are_normalised() {
+        let (request, service) = NewAppointmentRequest {
+            zipcode: " 27610 ".to_string(),
+            email: " user@example.com ".to_string(),
+            dates: vec![in_days(0), format!(" {} ", in_days(30))],
+            ..new_request()
+        }
+        .validate(&zipcodes())
+        .unwrap();
+
+        assert_eq!(request.zipcode, "27610");
+        assert_eq!(request.phone_number, "9195550100");
+        assert_eq!(request.email, "user@example.com");
+        assert_eq!(request.dates, vec![in_days(0), in_days(30)]);
+        assert_eq!(service.slug(), "renewal");
+        assert_eq!(request.service_title, "Driver License Renewal");
+        assert_eq!(request.selector, service.selector());
+        assert_eq!(request.status, Pending);
+        assert_eq!(request.status_history.len(), 1);
+    }
+
+    #[test]
+    fn invalid_fields_are_rejected() {
+        let cases: Vec<(NewAppointmentRequest, &str)> = vec![
+            (
+                NewAppointmentRequest {
+                    zipcode: "2761".to_string(),
+                    ..new_request()
+                },
+                "zipcode",
+            ),
+            (
+                NewAppointmentRequest {
+                    zipcode: "2761a".to_string(),
+                    ..new_request()
+                },
+                "zipcode",
+            ),
+            // Well-formed, but not in North Carolina.
+            (
+                NewAppointmentRequest {
+                    zipcode: "90210".to_string(),
+                    ..new_request()
+                },
+                "zipcode",
+            ),
+            (
+                NewAppointmentRequest {
+                    max_distance: 0,
+                    ..new_request()
+                },
+                "max_distance",
+            ),
+            (
+                NewAppointmentRequest {
+                    name: " ".to_string(),
+                    ..new_request()
+                },
+                "name",
+            ),
+            (
+                NewAppointmentRequest {
+                    name: "A_".repeat(51),
+                    ..new_request()
+                },
+                "name",
+            ),
+            (
+                NewAppointmentRequest {
+                    name: "Test User".to_string(),
+                    ..new_request()
+                },
+                "name",
+            ),
+            (
+                NewAppointmentRequest {
+                    name: "Test_".to_string(),
+                    ..new_request()
+                },
+                "name",
+            ),
+            (
+                NewAppointmentRequest {
+                    phone_number: "555-0100".to_string(),
+                    ..new_request()
+                },
+                "phone_number",
+            ),
+            (
+                NewAppointmentRequest {
+                    phone_number: "1 919 555 0100".to_string(),
+                    ..new_request()
+                },
+                "phone_number",
+            ),
+            (
+                NewAppointmentRequest {
+                    email: "user@example".to_string(),
+                    ..new_request()
+                },
+                "email",
+            ),
+            (
+                NewAppointmentRequest {
+                    email: "user @example.com".to_string(),
+                    ..new_request()
+                },
+                "email",
+            ),
+            (
+                NewAppointmentRequest {
+                    service_title: "Boat License".to_string(),
+                    ..new_request()
+                },
+                "service_title",
+            ),
+            (
+                NewAppointmentRequest {
+                    dates: Vec::new(),
+                    ..new_request()
+                },
+                "dates",
+            ),
+            (
+                NewAppointmentRequest {
+                    dates: vec!["12/01/2030".to_string()],
+                    ..new_request()
+                },
+                "dates",
+            ),
+            (
+                NewAppointmentRequest {
+                    dates: vec![in_days(-1)],
+                    ..new_request()
+                },
+                "dates",
+            ),
+        ];
+
+        for (request, field) in cases {
+            let description = format!("{:?}", request);
+            assert_eq!(rejected(request), vec![field], "{}", description);
+        }
+    }
+
+    #[test]
+    fn every_invalid_field_is_reported() {
+        let request = NewAppointmentRequest {
+            zipcode: "abc".to_string(),
+            max_distance: 0,
+            name: String::new(),
+            phone_number: String::new(),
+            email: String::new(),
+            service_title: String::new(),
+            dates: vec![in_days(30), "soon".to_string()],
+            dry_run: false,
+        };
+
+        assert_eq!(
+            rejected(request),
+            vec![
+                "zipcode",
+                "max_distance",
+                "name",
+                "phone_number",
+                "email",
+                "service_title",
+                "dates"
+            ]
+        );
+    }
+
+    #[test]
+    fn lifecycle_transitions() {
+        let allowed = [
+            (Pending, Scanning),
+            (Pending, Failed),
+            (Pending, Expired),
+            (Pending, Cancelled),
+            (Scanning, Pending),
+            (Scanning, Booking),
+            (Scanning, Failed),
+            (Scanning, Expired),
+            (Scanning, Cancelled),
+            (Booking, Pending),
+            (Booking, Scanning),
+            (Booking, Booked),
+            (Booking, Failed),
+            (Booking, Expired),
+            (Booking, Cancelled),
+        ];
+
+        for from in ALL {
+            for to in ALL {
+                assert_eq!(
+                    from.can_transition_to(to),
+                    allowed.contains(&(from, to)),
+                    "{:?} -> {:?}",
+                    from,
+                    to
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn terminal_states_reject_everything() {
+        for from in [Booked, Failed, Expired, Cancelled] {
+            for to in ALL {
+                let mut request = request(from);
+                assert!(
+                    request.transition(to, None).is_err(),
+                    "{:?} -> {:?}",
+                    from,
+                    to
+                );
+                assert_eq!(request.status, from);
+                assert!(request.status_history.is_empty());
+            }
+        }
+    }
+
+    #[test]
+    fn transitions_are_recorded() {
+        let mut request = request(Pending);
+        request.transition(Scanning, None).unwrap();
+        request.transition(Booking, None).unwrap();
+
+        let history: Vec<(Option<RequestStatus>, RequestStatus)> = request
+            .status_history
+            .iter()
+            .map(|t| (t.from, t.to))
+            .collect();
+        assert_eq!(
+            history,
+            vec![(Some(Pending), Scanning), (Some(Scanning), Booking)]
+        );
+        assert_eq!(request.status, Booking);
+
+        request.transition(Pending, None).unwrap();
+        let err = request.transition(Booked, None).unwrap_err();
+        assert_eq!((err.from, err.to), (Pending, Booked));
+        assert_eq!(request.status_history.len(), 3);
+    }
+
+    #[test]
+    fn failure_reason_is_only_set_on_failure() {
+        let mut request = request(Pending);
+        request
+            .transition(Scanning, Some("ignored".to_string()))
+            .unwrap();
+        assert_eq!(request.failure_reason, None);
+
+        request
+            .transition(Failed, Some("portal outage".to_string()))
+            .unwrap();
+        assert_eq!(request.failure_reason.as_deref(), Some("portal outage"));
+    }
+}
@@ -1,110 +1,140 @@
-/// Represents different types of driver's license services
-#[derive(Debug, Clone, PartialEq)]
-pub enum DMVService {
-    /// First time application for a driver's license
-    FirstTime {
-        title: &'static str,
-        selector: &'static str,
-    },
-    /// Duplicate of an existing license
-    Duplicate {
-        title: &'static str,
-        selector: &'static str,
-    },
-    /// Renewal of an existing license
-    Renewal {
-        title: &'static str,
-        selector: &'static str,
-    },
-    /// Fee-related services
-    Fees {
-        title: &'static str,
-        selector: &'static str,
-    },
-    /// ID card services
-    IdCard {
-        title: &'static str,
-        selector: &'static str,
-    },
-    /// Knowledge and computer test services
-    KnowledgeTest {
-        title: &'static str,
-        selector: &'static str,
-    },
-    /// Legal presence verification for non-citizens
-    LegalPresence {
-        title: &'static str,
-        selector: &'static str,
-    },
-    /// Motorcycle skills test scheduling
-    MotorcycleTest {
-        title: &'static str,
-        selector: &'static str,
-    },
-    /// Non-CDL road test scheduling
-    NonCdlRoadTest {
-        title: &'static str,
-        selector: &'static str,
-    },
-    /// Permit services
-    Permits {
-        title: &'static str,
-        selector: &'static str,
-    },
-    /// Teen driver level 1 services
-    TeenDriverLevel1 {
-        title: &'static str,
-        selector: &'static str,
-    },
-    /// Teen driver level 2 services
-    TeenDriverLevel2 {
-        title: &'static str,
-        selector: &'static str,
-    },
-    /// Teen driver level 3 services
-    TeenDriverLevel3 {
-        title: &'static str,
-        selector: &'static str,
-    },
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+
+/// One service offered on the NC DMV portal.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct ServiceDefinition {
+    /// Stable identifier used in APIs.
+    pub slug: String,
+    /// Display title, also stored on older requests.
+    pub title: String,
+    /// Text unique to the service's tile on the portal.
+    pub selector: String,
+    pub description: String,
+}
+
+#[derive(Deserialize)]
+struct ServiceCatalog {
+    service: Vec<ServiceDefinition>,
+}
+
+/// Every service, loaded once from `services.toml`.
+static CATALOG: Lazy<Vec<ServiceDefinition>> = Lazy::new(|| {
+    let catalog: ServiceCatalog =
+        toml::from_str(include_str!("services.toml")).expect("invalid services.toml");
+    catalog.service
+});
+
+/// A service from the catalog.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DMVService {
+    definition: &'static ServiceDefinition,
 }
 
-/// Implementation for DMVService
 impl DMVService {
+    /// Every service in catalog order.
+    pub fn all() -> impl Iterator<Item = DMVService> {
+        CATALOG.iter().map(|definition| DMVService { definition })
+    }
+
+    pub fn definition(&self) -> &'static ServiceDefinition {
+        self.definition
+    }
+
+    pub fn slug(&self) -> &'static str {
+        &self.definition.slug
+    }
+
     /// Gets the title of the service
-    pub fn _title(&self) -> &'static str {
-        match self {
-            DMVService::FirstTime { title, .. } => title,
-            DMVService::Duplicate { title, .. } => title,
-            DMVService::Renewal { title, .. } => title,
-            DMVService::Fees { title, .. } => title,
-            DMVService::IdCard { title, .. } => title,
-            DMVService::KnowledgeTest { title, .. } => title,
-            DMVService::LegalPresence { title, .. } => title,
-            DMVService::MotorcycleTest { title, .. } => title,
-            DMVService::NonCdlRoadTest { title, .. } => title,
-            DMVService::Permits { title, .. } => title,
-            DMVService::TeenDriverLevel1 { title, .. } => title,
-            DMVService::TeenDriverLevel2 { title, .. } => title,
-            DMVService::TeenDriverLevel3 { title, .. } => title,
-        }
+    pub fn title(&self) -> &'static str {
+        &self.definition.title
     }
 
     /// Gets the selector string of the service
     pub fn selector(&self) -> &'static str {
-        match self {
-            DMVService::FirstTime { selector, .. } => selector,
-            DMVService::Duplicate { selector, .. } => selector,
-            DMVService::Renewal { selector, .. } => selector,
-            DMVService::Fees { selector, .. } => selector,
-            DMVService::IdCard { selector, .. } => selector,
-            DMVService::KnowledgeTest { selector, .. } => selector,
-            DMVService::LegalPresence { selector, .. } => selector,
-            DMVService::MotorcycleTest { selector, .. } => selector,
-            DMVService::NonCdlRoadTest { selector, .. } => selector,
-            DMVService::Permits { selector, .. } => selector,
-            DMVService::TeenDriverLevel1 { selector, .. } => selector,
-            DMVService::TeenDriverLevel2 { selector, .. } => selector,
-            DMVService::TeenDriverLevel3 { selector, .. } => selector,
+        &self.definition.selector
+    }
+}
+
+/// Accepts a slug or a title.
+impl FromStr for DMVService {
+    type Err = ServiceNotFoundError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        DMVService::all()
+            .find(|service| service.slug() == s || service.title() == s)
+            .ok_or_else(|| ServiceNotFoundError {
+                title: s.to_string(),
+            })
+    }
+}
+
+// Custom error type for an unknown service title.
+#[derive(Debug)]
+pub struct ServiceNotFoundError {
+    pub title: String,
+}
+
+impl fmt::Display for ServiceNotFoundError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Service with title '{}' not found", self.title)
+    }
+}
+
+impl Error for ServiceNotFoundError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scraping::fixtures::fixture;
+    use scraper::{Html, Selector};
+    use std::collections::HashSet;
+
+    #[test]
+    fn catalog_entries_are_unique_and_filled_in() {
+        let services: Vec<_> = DMVService::all().collect();
+        assert!(!services.is_empty());
+
+        for field in [DMVService::slug, DMVService::title, DMVService::selector] {
+            let values: Vec<_> = services.iter().map(field).collect();
+            assert!(values.iter().all(|value| !value.trim().is_empty()));
+            assert_eq!(
+                values.iter().collect::<HashSet<_>>().len(),
+                values.len(),
+                "{:?}",
+                values
+            );
+        }
+    }
+
+    #[test]
+    fn selectors_only_match_their_own_tile() {
+        let document = Html::parse_document(fixture!("service_selection.html"));
+        let tile = Selector::parse("div.form-control-child").unwrap();
+        let tiles: Vec<String> = document
+            .select(&tile)
+            .map(|tile| tile.text().collect())
+            .collect();
+
+        for service in DMVService::all() {
+            let matches: Vec<_> = tiles
+                .iter()
+                .filter(|tile| tile.contains(service.selector()))
+                .collect();
+            assert_eq!(matches.len(), 1, "{}: {:?}", service.slug(), matches);
+            assert!(matches[0].contains(service.title()), "{}", service.slug());
+        }
+    }
+
+    #[test]
+    fn slugs_and_titles_parse_back() {
+        for service in DMVService::all() {
+            assert_eq!(service.slug().parse::<DMVService>().unwrap(), service);
+            assert_eq!(service.title().parse::<DMVService>().unwrap(), service);
         }
+        assert!("boat-license".parse::<DMVService>().is_err());
     }
 }
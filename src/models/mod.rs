@@ -1,3 +1,4 @@
+pub mod appointment;
 pub mod dmvservice;
 pub mod email;
 pub mod offices;
@@ -1,7 +1,7 @@
-use chrono::NaiveDate;
+use chrono::{DateTime, NaiveDate, Utc};
 use serde::Serialize;
 
-#[derive(Debug, Serialize, Clone)]
+#[derive(Debug, Serialize, Clone, PartialEq)]
 pub struct OfficeAvailability {
     pub is_reservable: bool,
     pub office_name: String,
@@ -10,4 +10,48 @@ pub struct OfficeAvailability {
     pub zip_code: String,
     pub available_dates: Vec<NaiveDate>,
     pub selected_date: Option<NaiveDate>,
+    /// Title of the service this office was scanned for.
+    pub service: String,
+    /// Request ID of the listener that scanned it.
+    pub source_listener: String,
+    pub scraped_at: DateTime<Utc>,
+}
+
+impl OfficeAvailability {
+    /// Whether `other` shows different availability, ignoring who scanned it and when.
+    pub fn availability_differs(&self, other: &OfficeAvailability) -> bool {
+        self.is_reservable != other.is_reservable
+            || self.available_dates != other.available_dates
+            || self.selected_date != other.selected_date
+            || self.street_address != other.street_address
+    }
+}
+
+#[cfg(test)]
+impl OfficeAvailability {
+    /// A reservable Renewal office without dates, scanned just now.
+    pub fn sample(name: &str) -> Self {
+        OfficeAvailability {
+            is_reservable: true,
+            office_name: name.to_string(),
+            street_address: "1240 US-70".to_string(),
+            distance: 8,
+            zip_code: "27529".to_string(),
+            available_dates: Vec::new(),
+            selected_date: None,
+            service: "Driver License Renewal".to_string(),
+            source_listener: "test".to_string(),
+            scraped_at: Utc::now(),
+        }
+    }
+}
+
+/// A cached office together with how fresh it is, returned by `/offices/all`.
+#[derive(Debug, Serialize, Clone)]
+pub struct CachedOffice {
+    #[serde(flatten)]
+    pub office: OfficeAvailability,
+    pub age_secs: i64,
+    /// Older than `office_stale_after_secs`; the listener may have stopped.
+    pub stale: bool,
 }
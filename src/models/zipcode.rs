@@ -13,14 +13,26 @@ pub fn load_zipcode_data(path: &str) -> ZipCodeData {
 
     let mut map = HashMap::new();
 
-    for result in reader.records() {
-        if let Ok(record) = result {
-            let zip = record.get(0).unwrap().to_string();
-            let lat: f64 = record.get(1).unwrap().parse().unwrap_or(0.0);
-            let lon: f64 = record.get(2).unwrap().parse().unwrap_or(0.0);
-            map.insert(zip, (lat, lon));
-        }
+    for record in reader.records().flatten() {
+        let zip = record.get(0).unwrap().to_string();
+        let lat: f64 = record.get(1).unwrap().parse().unwrap_or(0.0);
+        let lon: f64 = record.get(2).unwrap().parse().unwrap_or(0.0);
+        map.insert(zip, (lat, lon));
     }
 
     Arc::new(map)
 }
+
+/// Great-circle distance in miles between two ZIP codes, if both are known.
+pub fn distance_between(data: &ZipCodeData, from: &str, to: &str) -> Option<f64> {
+    const EARTH_RADIUS_MILES: f64 = 3958.8;
+
+    let (lat1, lon1) = data.get(from)?;
+    let (lat2, lon2) = data.get(to)?;
+    let (lat1, lat2) = (lat1.to_radians(), lat2.to_radians());
+    let dlat = lat2 - lat1;
+    let dlon = (lon2 - lon1).to_radians();
+
+    let a = (dlat / 2.0).sin().powi(2) + lat1.cos() * lat2.cos() * (dlon / 2.0).sin().powi(2);
+    Some(2.0 * EARTH_RADIUS_MILES * a.sqrt().asin())
+}
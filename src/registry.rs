@@ -0,0 +1,333 @@
+use chrono::{DateTime, Utc};
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use tokio::time::Instant;
+use tokio_util::sync::CancellationToken;
+
+use crate::models::offices::OfficeAvailability;
+
+/// How long a DELETE waits for the scraper to quit its Chrome session.
+const STOP_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// How long the status of a finished listener stays available.
+const FINISHED_TTL: Duration = Duration::from_secs(3600);
+
+pub static LISTENERS: Lazy<ListenerRegistry> = Lazy::new(ListenerRegistry::default);
+
+/// What a listener is currently doing.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ListenerPhase {
+    Starting,
+    Navigating,
+    Scanning,
+    Booking,
+    Booked,
+    Expired,
+    Stopping,
+    Stopped,
+    Failed,
+}
+
+/// Snapshot of a listener's health, returned by `GET /listen/requests/{id}`.
+#[derive(Debug, Clone, Serialize)]
+pub struct ListenerStatus {
+    pub request_id: String,
+    pub phase: ListenerPhase,
+    pub started_at: DateTime<Utc>,
+    pub last_scrape_at: Option<DateTime<Utc>>,
+    pub error_count: u32,
+    pub last_error: Option<String>,
+    pub last_results: Vec<OfficeAvailability>,
+}
+
+impl ListenerPhase {
+    /// Phases after which the listener never reports again.
+    pub fn is_final(self) -> bool {
+        matches!(
+            self,
+            ListenerPhase::Booked
+                | ListenerPhase::Expired
+                | ListenerPhase::Stopped
+                | ListenerPhase::Failed
+        )
+    }
+}
+
+/// Shared handle the scraper uses to report progress and observe cancellation.
+#[derive(Debug, Clone)]
+pub struct ListenerTracker {
+    status: Arc<RwLock<ListenerStatus>>,
+    cancel: CancellationToken,
+    phase_subscribers: Arc<Mutex<Vec<mpsc::UnboundedSender<ListenerPhase>>>>,
+}
+
+impl ListenerTracker {
+    pub fn new(request_id: String) -> Self {
+        ListenerTracker {
+            status: Arc::new(RwLock::new(ListenerStatus {
+                request_id,
+                phase: ListenerPhase::Starting,
+                started_at: Utc::now(),
+                last_scrape_at: None,
+                error_count: 0,
+                last_error: None,
+                last_results: Vec::new(),
+            })),
+            cancel: CancellationToken::new(),
+            phase_subscribers: Arc::new(Mutex::new(Vec::new())),
+        }
+    }
+
+    pub fn request_id(&self) -> String {
+        self.status.read().unwrap().request_id.clone()
+    }
+
+    pub fn snapshot(&self) -> ListenerStatus {
+        self.status.read().unwrap().clone()
+    }
+
+    pub fn set_phase(&self, phase: ListenerPhase) {
+        {
+            let mut status = self.status.write().unwrap();
+            if status.phase == phase {
+                return;
+            }
+            status.phase = phase;
+        }
+        self.phase_subscribers
+            .lock()
+            .unwrap()
+            .retain(|subscriber| subscriber.send(phase).is_ok());
+    }
+
+    /// Receives every phase change from now on, in order.
+    pub fn subscribe(&self) -> mpsc::UnboundedReceiver<ListenerPhase> {
+        let (tx, rx) = mpsc::unbounded_channel();
+        self.phase_subscribers.lock().unwrap().push(tx);
+        rx
+    }
+
+    pub fn record_scrape(&self, results: &[OfficeAvailability]) {
+        let mut status = self.status.write().unwrap();
+        status.last_scrape_at = Some(Utc::now());
+        status.last_results = results.to_vec();
+    }
+
+    pub fn record_error(&self, error: impl ToString) {
+        let mut status = self.status.write().unwrap();
+        status.error_count += 1;
+        status.last_error = Some(error.to_string());
+    }
+
+    /// Marks the listener as failed with a final error.
+    pub fn fail(&self, error: impl ToString) {
+        self.record_error(error);
+        self.set_phase(ListenerPhase::Failed);
+    }
+
+    pub fn cancellation(&self) -> &CancellationToken {
+        &self.cancel
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancel.is_cancelled()
+    }
+
+    /// Sleeps for `duration`, returning `false` early if the listener is cancelled.
+    pub async fn sleep(&self, duration: Duration) -> bool {
+        tokio::select! {
+            _ = self.cancel.cancelled() => false,
+            _ = tokio::time::sleep(duration) => true,
+        }
+    }
+}
+
+struct ListenerEntry {
+    tracker: ListenerTracker,
+    /// Taken out while `stop` waits for the task, so the entry stays visible.
+    handle: Option<JoinHandle<()>>,
+    /// When the registry first saw the task finished.
+    finished_at: Option<Instant>,
+}
+
+impl ListenerEntry {
+    fn is_finished(&self) -> bool {
+        self.handle.as_ref().is_some_and(JoinHandle::is_finished)
+    }
+
+    fn cancel(&self) {
+        if self.handle.is_some() && !self.is_finished() {
+            self.tracker.set_phase(ListenerPhase::Stopping);
+            self.tracker.cancel.cancel();
+        }
+    }
+}
+
+/// Waits until `deadline` for a cancelled listener to quit, aborting it otherwise.
+async fn join(
+    tracker: &ListenerTracker,
+    handle: &mut JoinHandle<()>,
+    deadline: Instant,
+) -> ListenerStatus {
+    if tokio::time::timeout_at(deadline, &mut *handle)
+        .await
+        .is_err()
+    {
+        tracing::warn!(
+            "Listener {} did not stop in time, aborting",
+            tracker.request_id()
+        );
+        handle.abort();
+    }
+
+    if !tracker.snapshot().phase.is_final() {
+        tracker.set_phase(ListenerPhase::Stopped);
+    }
+    tracker.snapshot()
+}
+
+/// In-process registry of spawned listeners, keyed by request ID.
+#[derive(Default)]
+pub struct ListenerRegistry {
+    listeners: Mutex<HashMap<String, ListenerEntry>>,
+}
+
+impl ListenerRegistry {
+    pub fn register(&self, tracker: ListenerTracker, handle: JoinHandle<()>) {
+        let request_id = tracker.request_id();
+        let mut listeners = self.listeners.lock().unwrap();
+        sweep(&mut listeners);
+        listeners.insert(
+            request_id,
+            ListenerEntry {
+                tracker,
+                handle: Some(handle),
+                finished_at: None,
+            },
+        );
+    }
+
+    pub fn status(&self, request_id: &str) -> Option<ListenerStatus> {
+        let mut listeners = self.listeners.lock().unwrap();
+        sweep(&mut listeners);
+        listeners
+            .get(request_id)
+            .map(|entry| entry.tracker.snapshot())
+    }
+
+    /// Cancels a listener and waits for it to quit its Chrome session.
+    ///
+    /// The listener stays registered, so its final status can still be read
+    /// until the sweep evicts it. Returns the final status, or `None` if no
+    /// listener has this ID.
+    pub async fn stop(&self, request_id: &str) -> Option<ListenerStatus> {
+        let (tracker, handle) = {
+            let mut listeners = self.listeners.lock().unwrap();
+            let entry = listeners.get_mut(request_id)?;
+            entry.cancel();
+            (entry.tracker.clone(), entry.handle.take())
+        };
+        // Another caller is already waiting for this listener.
+        let Some(mut handle) = handle else {
+            return Some(tracker.snapshot());
+        };
+
+        let status = join(&tracker, &mut handle, Instant::now() + STOP_TIMEOUT).await;
+        if let Some(entry) = self.listeners.lock().unwrap().get_mut(request_id) {
+            entry.handle = Some(handle);
+        }
+        Some(status)
+    }
+
+    /// Cancels every listener at once and waits until `deadline` for them to quit
+    /// their Chrome sessions. Listeners still running at the deadline are aborted.
+    pub async fn stop_all(&self, deadline: Instant) -> Vec<ListenerStatus> {
+        let entries: Vec<_> = self.listeners.lock().unwrap().drain().collect();
+        for (_, entry) in &entries {
+            entry.cancel();
+        }
+
+        let mut statuses = Vec::with_capacity(entries.len());
+        for (_, entry) in entries {
+            let status = match entry.handle {
+                Some(mut handle) => join(&entry.tracker, &mut handle, deadline).await,
+                None => entry.tracker.snapshot(),
+            };
+            statuses.push(status);
+        }
+        statuses
+    }
+}
+
+/// Drops listeners whose task finished more than `FINISHED_TTL` ago.
+fn sweep(listeners: &mut HashMap<String, ListenerEntry>) {
+    let now = Instant::now();
+    listeners.retain(|_, entry| {
+        if !entry.is_finished() {
+            return true;
+        }
+        let finished_at = *entry.finished_at.get_or_insert(now);
+        now.duration_since(finished_at) < FINISHED_TTL
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test(start_paused = true)]
+    async fn finished_listeners_are_evicted_after_a_while() {
+        let registry = ListenerRegistry::default();
+
+        let finished = ListenerTracker::new("finished".to_string());
+        let handle = tokio::spawn(async {});
+        tokio::time::sleep(Duration::from_millis(1)).await;
+        assert!(handle.is_finished());
+        finished.set_phase(ListenerPhase::Booked);
+        registry.register(finished, handle);
+
+        let running = ListenerTracker::new("running".to_string());
+        registry.register(running, tokio::spawn(std::future::pending()));
+
+        // Still there for clients polling the outcome.
+        assert_eq!(
+            registry.status("finished").map(|status| status.phase),
+            Some(ListenerPhase::Booked)
+        );
+        tokio::time::advance(FINISHED_TTL - Duration::from_secs(1)).await;
+        assert!(registry.status("finished").is_some());
+
+        tokio::time::advance(Duration::from_secs(1)).await;
+        assert!(registry.status("finished").is_none());
+        assert!(registry.status("running").is_some());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn stopped_listeners_stay_visible_until_evicted() {
+        let registry = ListenerRegistry::default();
+        let tracker = ListenerTracker::new("stopped".to_string());
+        let cancel = tracker.cancellation().clone();
+        registry.register(
+            tracker,
+            tokio::spawn(async move { cancel.cancelled().await }),
+        );
+
+        assert_eq!(
+            registry.stop("stopped").await.map(|status| status.phase),
+            Some(ListenerPhase::Stopped)
+        );
+        assert_eq!(
+            registry.status("stopped").map(|status| status.phase),
+            Some(ListenerPhase::Stopped)
+        );
+
+        tokio::time::advance(FINISHED_TTL).await;
+        assert!(registry.status("stopped").is_none());
+    }
+}
@@ -0,0 +1,272 @@
+use actix_web::dev::Payload;
+use actix_web::http::header;
+use actix_web::{FromRequest, HttpRequest, HttpResponse, delete, get, post, put, web};
+use futures_util::future::{Ready, ready};
+use serde::Serialize;
+use std::path::Path;
+
+use crate::config::AppConfig;
+use crate::error::ApiError;
+use crate::handlers::{canary, discovery};
+use crate::scraping::artifacts;
+use crate::scraping::selectors::{self, SelectorProfile};
+
+/// Extractor that rejects the request unless it carries `Authorization: Bearer
+/// <admin_token>`. Admin routes can change where scrapers send user details and
+/// serve captured pages, so every request is rejected when no token is configured.
+pub struct Admin;
+
+/// Compares two tokens in time that depends only on their lengths.
+fn tokens_match(provided: &[u8], expected: &[u8]) -> bool {
+    let mut diff = provided.len() ^ expected.len();
+    for (i, byte) in expected.iter().enumerate() {
+        diff |= usize::from(byte ^ provided.get(i).copied().unwrap_or(0));
+    }
+    diff == 0
+}
+
+impl FromRequest for Admin {
+    type Error = ApiError;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _payload: &mut Payload) -> Self::Future {
+        let expected = req
+            .app_data::<web::Data<AppConfig>>()
+            .and_then(|config| config.admin_token.clone());
+        let Some(expected) = expected.filter(|token| !token.is_empty()) else {
+            return ready(Err(ApiError::Unauthorized));
+        };
+
+        let provided = req
+            .headers()
+            .get(header::AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "));
+
+        ready(
+            if provided.is_some_and(|p| tokens_match(p.as_bytes(), expected.as_bytes())) {
+                Ok(Admin)
+            } else {
+                Err(ApiError::Unauthorized)
+            },
+        )
+    }
+}
+
+// --------------------------------------------------------------------------
+// Service discovery
+// --------------------------------------------------------------------------
+
+/// Returns the last comparison of the portal's service tiles with the catalog.
+#[get("/services/discovery")]
+async fn discovery_report(_admin: Admin) -> Result<HttpResponse, ApiError> {
+    let report = discovery::report().ok_or_else(|| ApiError::not_found("Discovery report"))?;
+    Ok(HttpResponse::Ok().json(report))
+}
+
+/// Runs service discovery now and returns its report.
+#[post("/services/discovery")]
+async fn run_discovery(
+    _admin: Admin,
+    config: web::Data<AppConfig>,
+) -> Result<HttpResponse, ApiError> {
+    let report = discovery::run_discovery(&config).await?;
+    Ok(HttpResponse::Ok().json(report))
+}
+
+// --------------------------------------------------------------------------
+// Selector canary
+// --------------------------------------------------------------------------
+
+/// Returns the last selector canary run.
+#[get("/canary")]
+async fn canary_report(_admin: Admin) -> Result<HttpResponse, ApiError> {
+    let report = canary::report().ok_or_else(|| ApiError::not_found("Canary report"))?;
+    Ok(HttpResponse::Ok().json(report))
+}
+
+/// Runs the selector canary now and returns its report.
+#[post("/canary")]
+async fn run_canary(_admin: Admin, config: web::Data<AppConfig>) -> Result<HttpResponse, ApiError> {
+    let report = canary::run_canary(&config).await?;
+    Ok(HttpResponse::Ok().json(report))
+}
+
+/// Returns the page source captured at the failing step of the last canary run.
+#[get("/canary/snapshot")]
+async fn canary_snapshot(_admin: Admin) -> Result<HttpResponse, ApiError> {
+    let snapshot = canary::report()
+        .and_then(|report| report.failure)
+        .map(|failure| failure.snapshot)
+        .ok_or_else(|| ApiError::not_found("Canary snapshot"))?;
+    Ok(HttpResponse::Ok()
+        .content_type("text/html; charset=utf-8")
+        .body(snapshot))
+}
+
+// --------------------------------------------------------------------------
+// Failure artifacts
+// --------------------------------------------------------------------------
+
+fn artifact_error(error: std::io::Error) -> ApiError {
+    match error.kind() {
+        std::io::ErrorKind::NotFound => ApiError::not_found("Artifact"),
+        std::io::ErrorKind::InvalidInput => ApiError::BadRequest(error.to_string()),
+        _ => ApiError::internal(error),
+    }
+}
+
+/// Lists the failure captures saved for a request, newest first.
+#[get("/artifacts/{request_id}")]
+async fn list_artifacts(
+    _admin: Admin,
+    config: web::Data<AppConfig>,
+    path: web::Path<String>,
+) -> Result<HttpResponse, ApiError> {
+    let captures = artifacts::list(Path::new(&config.artifact_dir), &path.into_inner())
+        .map_err(artifact_error)?;
+    Ok(HttpResponse::Ok().json(captures))
+}
+
+/// Serves one file of a capture: `screenshot.png`, `page.html` or `capture.json`.
+#[get("/artifacts/{request_id}/{capture_id}/{file}")]
+async fn artifact_file(
+    _admin: Admin,
+    config: web::Data<AppConfig>,
+    path: web::Path<(String, String, String)>,
+) -> Result<HttpResponse, ApiError> {
+    let (request_id, capture_id, file) = path.into_inner();
+    let bytes = artifacts::read_file(
+        Path::new(&config.artifact_dir),
+        &request_id,
+        &capture_id,
+        &file,
+    )
+    .map_err(artifact_error)?;
+
+    let content_type = match file.as_str() {
+        artifacts::SCREENSHOT_FILE => "image/png",
+        artifacts::HTML_FILE => "text/html; charset=utf-8",
+        _ => "application/json",
+    };
+    Ok(HttpResponse::Ok().content_type(content_type).body(bytes))
+}
+
+// --------------------------------------------------------------------------
+// Selector profile
+// --------------------------------------------------------------------------
+
+#[derive(Serialize)]
+struct SelectorStatus {
+    /// Whether `profile` is a hotfix rather than the configured profile.
+    hotfix: bool,
+    profile: SelectorProfile,
+}
+
+fn selector_status(config: &AppConfig) -> SelectorStatus {
+    SelectorStatus {
+        hotfix: selectors::hotfix().is_some(),
+        profile: selectors::active(config).as_ref().clone(),
+    }
+}
+
+/// Returns the selector profile scrapers are using.
+#[get("/selectors")]
+async fn get_selectors(_admin: Admin, config: web::Data<AppConfig>) -> HttpResponse {
+    HttpResponse::Ok().json(selector_status(&config))
+}
+
+/// Hotfixes the selector profile until restart. Fields left out keep their
+/// compiled-in defaults; running sessions pick it up on their next sweep.
+#[put("/selectors")]
+async fn put_selectors(
+    _admin: Admin,
+    config: web::Data<AppConfig>,
+    profile: web::Json<SelectorProfile>,
+) -> Result<HttpResponse, ApiError> {
+    let profile = profile.into_inner();
+    let errors = profile.validate();
+    if !errors.is_empty() {
+        return Err(ApiError::Validation(errors));
+    }
+
+    selectors::set_hotfix(Some(profile));
+    Ok(HttpResponse::Ok().json(selector_status(&config)))
+}
+
+/// Drops the hotfix and goes back to the configured profile.
+#[delete("/selectors")]
+async fn delete_selectors(_admin: Admin, config: web::Data<AppConfig>) -> HttpResponse {
+    selectors::set_hotfix(None);
+    HttpResponse::Ok().json(selector_status(&config))
+}
+
+pub fn init(cfg: &mut web::ServiceConfig) {
+    cfg.service(discovery_report)
+        .service(run_discovery)
+        .service(canary_report)
+        .service(run_canary)
+        .service(canary_snapshot)
+        .service(list_artifacts)
+        .service(artifact_file)
+        .service(get_selectors)
+        .service(put_selectors)
+        .service(delete_selectors);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::App;
+    use actix_web::http::StatusCode;
+    use actix_web::test::{TestRequest, call_service, init_service};
+
+    async fn status(admin_token: Option<&str>, authorization: Option<&str>) -> StatusCode {
+        let config = AppConfig {
+            admin_token: admin_token.map(str::to_string),
+            ..AppConfig::default()
+        };
+        let app = init_service(App::new().app_data(web::Data::new(config)).configure(init)).await;
+
+        let mut request = TestRequest::get().uri("/selectors");
+        if let Some(authorization) = authorization {
+            request = request.insert_header((header::AUTHORIZATION, authorization));
+        }
+        call_service(&app, request.to_request()).await.status()
+    }
+
+    #[actix_web::test]
+    async fn admin_routes_fail_closed() {
+        assert_eq!(status(None, None).await, StatusCode::UNAUTHORIZED);
+        assert_eq!(
+            status(None, Some("Bearer anything")).await,
+            StatusCode::UNAUTHORIZED
+        );
+        assert_eq!(
+            status(Some(""), Some("Bearer ")).await,
+            StatusCode::UNAUTHORIZED
+        );
+        assert_eq!(status(Some("s3cret"), None).await, StatusCode::UNAUTHORIZED);
+        assert_eq!(
+            status(Some("s3cret"), Some("Bearer s3cre")).await,
+            StatusCode::UNAUTHORIZED
+        );
+        assert_eq!(
+            status(Some("s3cret"), Some("Bearer s3cret!")).await,
+            StatusCode::UNAUTHORIZED
+        );
+        assert_eq!(
+            status(Some("s3cret"), Some("Bearer s3cret")).await,
+            StatusCode::OK
+        );
+    }
+
+    #[test]
+    fn tokens_match_exactly() {
+        assert!(tokens_match(b"abc", b"abc"));
+        assert!(!tokens_match(b"abd", b"abc"));
+        assert!(!tokens_match(b"ab", b"abc"));
+        assert!(!tokens_match(b"abc\0", b"abc"));
+        assert!(!tokens_match(b"", b"abc"));
+    }
+}
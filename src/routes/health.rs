@@ -1,10 +1,20 @@
 use actix_web::{HttpResponse, Responder, get, web};
 
+use crate::handlers::canary;
+
 #[get("/ping")]
 async fn ping() -> impl Responder {
     HttpResponse::Ok().body("pong")
 }
 
+/// Selector canary results for Prometheus.
+#[get("/metrics")]
+async fn metrics() -> impl Responder {
+    HttpResponse::Ok()
+        .content_type("text/plain; version=0.0.4")
+        .body(canary::metrics())
+}
+
 pub fn init(cfg: &mut web::ServiceConfig) {
-    cfg.service(ping);
+    cfg.service(ping).service(metrics);
 }
@@ -1,207 +1,222 @@
-use actix_web::{HttpResponse, Responder, get, web};
-use serde::{Deserialize, Serialize};
-use std::error::Error;
-use std::fmt;
-
-#[cfg(not(debug_assertions))]
-use mongodb::{Client, Collection, options::ClientOptions};
-#[cfg(not(debug_assertions))]
-use std::env;
-#[cfg(not(debug_assertions))]
-use tokio::sync::OnceCell;
-
-use crate::handlers::listen::listen;
+use actix_web::http::header;
+use actix_web::{HttpResponse, Responder, delete, get, post, web};
+use serde_json::json;
+
+use crate::config::AppConfig;
+use crate::error::ApiError;
+use crate::handlers::listen::{cancel, listen, status};
+use crate::handlers::resume;
+use crate::models::appointment::{AppointmentRequest, NewAppointmentRequest};
 use crate::models::dmvservice::DMVService;
+use crate::models::zipcode::ZipCodeData;
+use crate::store::RequestStore;
+
+/// Persists a validated request and starts its listener.
+async fn start_request(
+    store: web::Data<dyn RequestStore>,
+    config: web::Data<AppConfig>,
+    request: &AppointmentRequest,
+    service_type: DMVService,
+) -> Result<(), ApiError> {
+    store.insert(request).await.map_err(ApiError::database)?;
 
-// --------------------------------------------------------------------------
-// DMV Service Definition
-// --------------------------------------------------------------------------
-
-// Custom error type for an unknown service title.
-#[derive(Debug)]
-pub struct ServiceNotFoundError {
-    title: String,
-}
-
-impl fmt::Display for ServiceNotFoundError {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "Service with title '{}' not found", self.title)
-    }
+    // Call the listen function (business logic).
+    listen(
+        store.into_inner(),
+        config.into_inner(),
+        request,
+        service_type,
+    )
+    .await
+    .map(drop)
+    .map_err(ApiError::internal)
 }
 
-impl Error for ServiceNotFoundError {}
-
-/// Matches a service title to a DMVService variant.
-fn get_service_by_title(title: &str) -> Result<DMVService, ServiceNotFoundError> {
-    match title {
-        "Driver License - First Time" => Ok(DMVService::FirstTime {
-            title: "Driver License - First Time",
-            selector: "New driver over 18",
-        }),
-        "Driver License Duplicate" => Ok(DMVService::Duplicate {
-            title: "Driver License Duplicate",
-            selector: "Replace lost or stolen license",
-        }),
-        "Driver License Renewal" => Ok(DMVService::Renewal {
-            title: "Driver License Renewal",
-            selector: "Renew an existing license",
-        }),
-        "Fees" => Ok(DMVService::Fees {
-            title: "Fees",
-            selector: "License reinstatement appointment",
-        }),
-        "ID Card" => Ok(DMVService::IdCard {
-            title: "ID Card",
-            selector: "State ID card",
-        }),
-        "Knowledge/Computer Test" => Ok(DMVService::KnowledgeTest {
-            title: "Knowledge/Computer Test",
-            selector: "Written, traffic signs",
-        }),
-        "Legal Presence" => Ok(DMVService::LegalPresence {
-            title: "Legal Presence",
-            selector: "For non-citizens to prove",
-        }),
-        "Motorcycle Skills Test" => Ok(DMVService::MotorcycleTest {
-            title: "Motorcycle Skills Test",
-            selector: "Schedule a motorcycle driving skills test",
-        }),
-        "Non-CDL Road Test" => Ok(DMVService::NonCdlRoadTest {
-            title: "Non-CDL Road Test",
-            selector: "Schedule a driving skills test",
-        }),
-        "Permits" => Ok(DMVService::Permits {
-            title: "Permits",
-            selector: "Adult permit",
-        }),
-        "Teen Driver Level 1" => Ok(DMVService::TeenDriverLevel1 {
-            title: "Teen Driver Level 1",
-            selector: "Limited learner permit",
-        }),
-        "Teen Driver Level 2" => Ok(DMVService::TeenDriverLevel2 {
-            title: "Teen Driver Level 2",
-            selector: "Limited provisional license",
-        }),
-        "Teen Driver Level 3" => Ok(DMVService::TeenDriverLevel3 {
-            title: "Teen Driver Level 3",
-            selector: "Full provisional license",
-        }),
-        _ => Err(ServiceNotFoundError {
-            title: title.to_string(),
-        }),
-    }
+/// Creates an appointment request from a JSON body and starts listening for it.
+/// Only the ID and status are returned so contact details are never echoed back.
+#[post("/requests")]
+async fn create_request(
+    body: web::Json<NewAppointmentRequest>,
+    zipcodes: web::Data<ZipCodeData>,
+    store: web::Data<dyn RequestStore>,
+    config: web::Data<AppConfig>,
+) -> Result<HttpResponse, ApiError> {
+    let (request, service_type) = body
+        .into_inner()
+        .validate(&zipcodes)
+        .map_err(ApiError::Validation)?;
+
+    start_request(store, config, &request, service_type).await?;
+
+    Ok(HttpResponse::Created()
+        .insert_header((
+            header::LOCATION,
+            format!("/listen/requests/{}", request.request_id),
+        ))
+        .json(json!({ "id": request.request_id, "status": request.status })))
 }
 
-// --------------------------------------------------------------------------
-// MongoDB Asynchronous Client Initialization Using tokio::sync::OnceCell
-// --------------------------------------------------------------------------
-
-/// The appointment request document to be stored in MongoDB.
-#[derive(Debug, Serialize, Deserialize)]
-pub struct AppointmentRequest {
-    pub zipcode: String,
-    pub max_distance: u16,
-    pub name: String,
-    pub phone_number: String,
-    pub email: String,
-    pub service_title: String,
-    pub selector: String,
-    pub dates: Vec<String>,
+/// Reports whether a request's listener is alive and what it last saw. Finished
+/// listeners are forgotten an hour after they stop.
+#[get("/requests/{id}")]
+async fn get_request(path: web::Path<String>) -> Result<HttpResponse, ApiError> {
+    let status = status(&path).ok_or_else(|| ApiError::not_found("Listener"))?;
+    Ok(HttpResponse::Ok().json(status))
 }
 
-/// Global asynchronous MongoDB client using `OnceCell`.
-#[cfg(not(debug_assertions))]
-static MONGO_CLIENT: OnceCell<Client> = OnceCell::const_new();
-
-/// Asynchronously get or initialize the global MongoDB client.
-#[cfg(not(debug_assertions))]
-pub async fn get_mongo_client() -> &'static Client {
-    MONGO_CLIENT
-        .get_or_init(|| async {
-            let uri = env::var("MONGODB_URI").expect("MONGODB_URI must be set");
-            let client_options = ClientOptions::parse(&uri)
-                .await
-                .expect("Failed to parse MongoDB options");
-            Client::with_options(client_options).expect("Failed to initialize MongoDB client")
-        })
+/// Stops a request's listener, waiting for it to quit its Chrome session.
+#[delete("/requests/{id}")]
+async fn delete_request(
+    path: web::Path<String>,
+    store: web::Data<dyn RequestStore>,
+) -> Result<HttpResponse, ApiError> {
+    let status = cancel(store.get_ref(), &path)
         .await
+        .ok_or_else(|| ApiError::not_found("Listener"))?;
+    Ok(HttpResponse::Ok().json(status))
 }
 
-/// Asynchronously obtain the MongoDB collection for appointment requests.
-#[cfg(not(debug_assertions))]
-pub async fn get_appointment_collection() -> Collection<AppointmentRequest> {
-    let client = get_mongo_client().await;
-    let db = client.database("InstantDMV");
-    db.collection::<AppointmentRequest>("users_nc")
+/// Lists which persisted requests were resumed at startup and which failed.
+#[get("/resumed")]
+async fn resumed() -> impl Responder {
+    HttpResponse::Ok().json(resume::report())
 }
 
-// --------------------------------------------------------------------------
-// Actix-web Handler and Server Setup
-// --------------------------------------------------------------------------
-
+/// Legacy path-parameter route, kept as a thin adapter over `create_request`
+/// until the frontend has migrated to the JSON body.
 #[get("/test/{zipcode}/{max_distance}/{name}/{phone_number}/{email}/{service_title}/{dates}")]
 async fn test(
     path: web::Path<(String, u16, String, String, String, String, String)>,
-) -> impl Responder {
+    zipcodes: web::Data<ZipCodeData>,
+    store: web::Data<dyn RequestStore>,
+    config: web::Data<AppConfig>,
+) -> Result<HttpResponse, ApiError> {
     let (zipcode, max_distance, name, phone_number, email, service_title, dates_str) =
         path.into_inner();
 
-    // Parse the comma-separated dates.
-    let dates: Vec<String> = dates_str.split(',').map(|s| s.trim().to_string()).collect();
-
-    // Get the DMV service by title.
-    let service_type = match get_service_by_title(&service_title) {
-        Ok(service) => service,
-        Err(e) => {
-            eprintln!("Invalid service title: {}", e);
-            return HttpResponse::BadRequest().body(format!("Invalid service title: {}", e));
-        }
-    };
-
-    // Create an appointment request document.
-    #[cfg(not(debug_assertions))]
-    let new_request = AppointmentRequest {
-        zipcode: zipcode.clone(),
-        max_distance,
-        name: name.clone(),
-        phone_number: phone_number.clone(),
-        email: email.clone(),
-        service_title: service_title.clone(),
-        selector: service_type.selector().to_string(),
-        dates: dates.clone(),
-    };
-
-    // Insert the appointment request into MongoDB asynchronously if in release mode
-    #[cfg(not(debug_assertions))]
-    {
-        let collection = get_appointment_collection().await;
-        if let Err(e) = collection.insert_one(new_request).await {
-            eprintln!("Failed to insert into MongoDB: {:?}", e);
-            return HttpResponse::InternalServerError().body("Failed to store request.");
-        }
-    }
-
-    // Call the listen function (business logic).
-    match listen(
+    let body = NewAppointmentRequest {
         zipcode,
         max_distance,
         name,
         phone_number,
         email,
-        service_type,
-        dates,
-    )
-    .await
-    {
-        Ok(_) => HttpResponse::Ok().body("Started listening for appointments."),
-        Err(e) => {
-            eprintln!("Failed to start listener: {:?}", e);
-            HttpResponse::InternalServerError().body("Failed to start listener.")
-        }
-    }
+        service_title,
+        // Parse the comma-separated dates.
+        dates: dates_str.split(',').map(|s| s.trim().to_string()).collect(),
+        dry_run: false,
+    };
+
+    let (request, service_type) = body.validate(&zipcodes).map_err(ApiError::Validation)?;
+
+    start_request(store, config, &request, service_type).await?;
+    Ok(HttpResponse::Ok().body("Started listening for appointments."))
 }
 
 /// Configures the Actix Web application routes.
 pub fn init(cfg: &mut web::ServiceConfig) {
-    cfg.service(test);
+    cfg.service(create_request)
+        .service(get_request)
+        .service(delete_request)
+        .service(resumed)
+        .service(test);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::StoreBackend;
+    use crate::models::appointment::RequestStatus;
+    use crate::registry::{LISTENERS, ListenerTracker};
+    use crate::store::memory::MemoryRequestStore;
+    use actix_web::App;
+    use actix_web::http::StatusCode;
+    use actix_web::test::{TestRequest, call_service, init_service, read_body_json};
+    use serde_json::Value;
+    use std::collections::HashMap;
+    use std::sync::Arc;
+
+    #[actix_web::test]
+    async fn created_requests_do_not_echo_contact_details() {
+        let store: Arc<dyn RequestStore> = Arc::new(MemoryRequestStore::default());
+        let zipcodes: ZipCodeData =
+            Arc::new(HashMap::from([("27610".to_string(), (35.7449, -78.5486))]));
+        let config = AppConfig {
+            request_store: Some(StoreBackend::Memory),
+            dry_run: true,
+            ..AppConfig::default()
+        };
+        let app = init_service(
+            App::new()
+                .app_data(web::Data::from(store.clone()))
+                .app_data(web::Data::new(zipcodes))
+                .app_data(web::Data::new(config))
+                .configure(init),
+        )
+        .await;
+
+        let request = TestRequest::post()
+            .uri("/requests")
+            .set_json(json!({
+                "zipcode": "27610",
+                "max_distance": 25,
+                "name": "Test_User",
+                "phone_number": "919-555-0100",
+                "email": "user@example.com",
+                "service_title": "renewal",
+                "dates": ["2099-01-01"],
+                "dry_run": true
+            }))
+            .to_request();
+        let response = call_service(&app, request).await;
+        assert_eq!(response.status(), StatusCode::CREATED);
+        let location = response
+            .headers()
+            .get(header::LOCATION)
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or_default()
+            .to_string();
+
+        let body: Value = read_body_json(response).await;
+        let id = body["id"].as_str().unwrap();
+        assert_eq!(body, json!({ "id": id, "status": "pending" }));
+        assert_eq!(location, format!("/listen/requests/{}", id));
+        assert!(store.get(id).await.unwrap().is_some());
+
+        LISTENERS.stop(id).await;
+    }
+
+    #[actix_web::test]
+    async fn stopped_requests_can_still_be_read() {
+        let store: Arc<dyn RequestStore> = Arc::new(MemoryRequestStore::default());
+        let request = AppointmentRequest::sample(&uuid::Uuid::new_v4().to_string());
+        let id = request.request_id.clone();
+        store.insert(&request).await.unwrap();
+
+        let tracker = ListenerTracker::new(id.clone());
+        let cancel = tracker.cancellation().clone();
+        LISTENERS.register(
+            tracker,
+            tokio::spawn(async move { cancel.cancelled().await }),
+        );
+
+        let app = init_service(
+            App::new()
+                .app_data(web::Data::from(store.clone()))
+                .configure(init),
+        )
+        .await;
+
+        let uri = format!("/requests/{}", id);
+        let response = call_service(&app, TestRequest::delete().uri(&uri).to_request()).await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let response = call_service(&app, TestRequest::get().uri(&uri).to_request()).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let body: Value = read_body_json(response).await;
+        assert_eq!(body["phase"], "stopped");
+        assert_eq!(
+            store.get(&id).await.unwrap().map(|request| request.status),
+            Some(RequestStatus::Cancelled)
+        );
+    }
 }
@@ -1,11 +1,23 @@
+pub mod admin;
 pub mod health;
 pub mod listen;
 pub mod offices;
+pub mod services;
 
-use actix_web::web;
+use actix_web::{HttpResponse, web};
+
+use crate::error::{ApiError, extractor_error};
 
 pub fn init(cfg: &mut web::ServiceConfig) {
-    cfg.service(web::scope("/health").configure(health::init))
+    cfg.app_data(web::JsonConfig::default().error_handler(extractor_error))
+        .app_data(web::PathConfig::default().error_handler(extractor_error))
+        .app_data(web::QueryConfig::default().error_handler(extractor_error))
+        .service(web::scope("/admin").configure(admin::init))
+        .service(web::scope("/health").configure(health::init))
         .service(web::scope("/offices").configure(offices::init))
-        .service(web::scope("/listen").configure(listen::init));
+        .service(web::scope("/listen").configure(listen::init))
+        .service(web::scope("/services").configure(services::init))
+        .default_service(web::to(|| async {
+            Err::<HttpResponse, _>(ApiError::NotFound("Route not found".to_string()))
+        }));
 }
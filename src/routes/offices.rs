@@ -1,14 +1,57 @@
-use crate::handlers::offices::get_available_appointments;
-use actix_web::{HttpResponse, Responder, get, web};
+use crate::error::ApiError;
+use crate::handlers::offices::{
+    OfficeFilter, OfficePage, get_available_appointments, office_stream,
+};
+use crate::models::zipcode::ZipCodeData;
+use actix_web::http::header;
+use actix_web::{HttpRequest, HttpResponse, get, web};
 
+/// Lists cached offices; the total number of matches before pagination is
+/// returned in `X-Total-Count`.
 #[get("/all")]
-async fn offices() -> impl Responder {
-    match get_available_appointments().await {
-        Ok(data) => HttpResponse::Ok().json(data),
-        Err(e) => HttpResponse::InternalServerError().body(format!("Error: {}", e)),
+async fn offices(
+    filter: web::Query<OfficeFilter>,
+    page: web::Query<OfficePage>,
+    zipcodes: web::Data<ZipCodeData>,
+) -> Result<HttpResponse, ApiError> {
+    let mut errors = filter.validate(&zipcodes).err().unwrap_or_default();
+    errors.extend(page.validate().err().unwrap_or_default());
+    if !errors.is_empty() {
+        return Err(ApiError::Validation(errors));
     }
+
+    let (data, total) = get_available_appointments(&filter, &page, &zipcodes).await?;
+    Ok(HttpResponse::Ok()
+        .insert_header(("X-Total-Count", total.to_string()))
+        .json(data))
+}
+
+/// Pushes office availability changes as server-sent events.
+#[get("/stream")]
+async fn stream(
+    req: HttpRequest,
+    filter: web::Query<OfficeFilter>,
+    zipcodes: web::Data<ZipCodeData>,
+) -> Result<HttpResponse, ApiError> {
+    let filter = filter.into_inner();
+    filter.validate(&zipcodes).map_err(ApiError::Validation)?;
+
+    let last_event_id = req
+        .headers()
+        .get("Last-Event-ID")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.trim().parse().ok());
+
+    Ok(HttpResponse::Ok()
+        .content_type("text/event-stream")
+        .insert_header((header::CACHE_CONTROL, "no-cache"))
+        .streaming(office_stream(
+            filter,
+            last_event_id,
+            zipcodes.get_ref().clone(),
+        )))
 }
 
 pub fn init(cfg: &mut web::ServiceConfig) {
-    cfg.service(offices);
+    cfg.service(offices).service(stream);
 }
@@ -0,0 +1,12 @@
+use crate::handlers::services::list_services;
+use actix_web::{HttpResponse, Responder, get, web};
+
+/// Lists every service a request can be made for.
+#[get("")]
+async fn services() -> impl Responder {
+    HttpResponse::Ok().json(list_services())
+}
+
+pub fn init(cfg: &mut web::ServiceConfig) {
+    cfg.service(services);
+}
@@ -0,0 +1,291 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+use thirtyfour::prelude::*;
+use tracing::{error, info};
+
+use crate::config::AppConfig;
+use crate::scraping::page::PageState;
+use crate::scraping::selectors::{self, SelectorProfile};
+
+pub const SCREENSHOT_FILE: &str = "screenshot.png";
+pub const HTML_FILE: &str = "page.html";
+pub const META_FILE: &str = "capture.json";
+
+/// What the browser showed when a scrape failed or the portal showed an error
+/// message. Saved with the page source and
+/// a screenshot under `<artifact_dir>/<request_id>/<id>/`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CaptureMeta {
+    /// Directory name of the capture; sorts by capture time.
+    pub id: String,
+    pub request_id: String,
+    pub captured_at: DateTime<Utc>,
+    /// The scrape error or portal message that triggered the capture.
+    pub error: String,
+    pub url: Option<String>,
+    /// What the page was recognised as.
+    pub page: PageState,
+    /// Whether a screenshot could be taken.
+    pub screenshot: bool,
+}
+
+pub struct Capture {
+    pub meta: CaptureMeta,
+    pub html: String,
+    pub screenshot: Option<Vec<u8>>,
+}
+
+/// Whether `segment` can be used as a directory name under the artifact root.
+/// Request and capture IDs come from URLs, so anything else is rejected.
+pub fn is_safe_segment(segment: &str) -> bool {
+    !segment.is_empty()
+        && segment.len() <= 128
+        && segment
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+fn invalid(what: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, format!("invalid {}", what))
+}
+
+/// Reads what the browser is showing. Parts the driver can't provide are left empty.
+pub async fn capture(
+    driver: &WebDriver,
+    selectors: &SelectorProfile,
+    request_id: &str,
+    reason: &str,
+) -> Capture {
+    let html = driver.source().await.unwrap_or_default();
+    let screenshot = driver.screenshot_as_png().await.ok();
+    let captured_at = Utc::now();
+
+    Capture {
+        meta: CaptureMeta {
+            id: captured_at.format("%Y%m%dT%H%M%S%3fZ").to_string(),
+            request_id: request_id.to_string(),
+            captured_at,
+            error: reason.to_string(),
+            url: driver.current_url().await.ok().map(|url| url.to_string()),
+            page: PageState::detect(&html, selectors),
+            screenshot: screenshot.is_some(),
+        },
+        html,
+        screenshot,
+    }
+}
+
+/// Writes `capture` under `root` and applies the retention limits.
+pub fn save(
+    root: &Path,
+    capture: &Capture,
+    max_per_request: usize,
+    max_age: Duration,
+) -> io::Result<PathBuf> {
+    let meta = &capture.meta;
+    if !is_safe_segment(&meta.request_id) {
+        return Err(invalid("request ID"));
+    }
+
+    let dir = root.join(&meta.request_id).join(&meta.id);
+    std::fs::create_dir_all(&dir)?;
+    std::fs::write(dir.join(HTML_FILE), &capture.html)?;
+    if let Some(screenshot) = &capture.screenshot {
+        std::fs::write(dir.join(SCREENSHOT_FILE), screenshot)?;
+    }
+    std::fs::write(dir.join(META_FILE), serde_json::to_vec_pretty(meta)?)?;
+
+    prune(root, max_per_request, max_age)?;
+    Ok(dir)
+}
+
+/// Removes captures older than `max_age` and all but the newest `max_per_request`
+/// of each request, then any request directories left empty.
+pub fn prune(root: &Path, max_per_request: usize, max_age: Duration) -> io::Result<()> {
+    let Ok(requests) = std::fs::read_dir(root) else {
+        return Ok(());
+    };
+
+    for request in requests.flatten() {
+        let request_dir = request.path();
+        if !request_dir.is_dir() {
+            continue;
+        }
+
+        let mut captures = capture_dirs(&request_dir)?;
+        // Newest first; IDs sort by capture time.
+        captures.sort_by(|a, b| b.file_name().cmp(&a.file_name()));
+        for (i, capture) in captures.iter().enumerate() {
+            let expired = std::fs::metadata(capture)
+                .and_then(|m| m.modified())
+                .ok()
+                .and_then(|modified| SystemTime::now().duration_since(modified).ok())
+                .is_some_and(|age| age >= max_age);
+            if i >= max_per_request || expired {
+                std::fs::remove_dir_all(capture)?;
+            }
+        }
+
+        if std::fs::read_dir(&request_dir)?.next().is_none() {
+            std::fs::remove_dir(&request_dir)?;
+        }
+    }
+    Ok(())
+}
+
+fn capture_dirs(request_dir: &Path) -> io::Result<Vec<PathBuf>> {
+    Ok(std::fs::read_dir(request_dir)?
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.is_dir())
+        .collect())
+}
+
+/// Captures saved for `request_id`, newest first.
+pub fn list(root: &Path, request_id: &str) -> io::Result<Vec<CaptureMeta>> {
+    if !is_safe_segment(request_id) {
+        return Err(invalid("request ID"));
+    }
+    let request_dir = root.join(request_id);
+    if !request_dir.is_dir() {
+        return Ok(Vec::new());
+    }
+
+    let mut captures: Vec<CaptureMeta> = capture_dirs(&request_dir)?
+        .into_iter()
+        .filter_map(|dir| std::fs::read(dir.join(META_FILE)).ok())
+        .filter_map(|meta| serde_json::from_slice(&meta).ok())
+        .collect();
+    captures.sort_by(|a, b| b.id.cmp(&a.id));
+    Ok(captures)
+}
+
+/// One file of a capture: the screenshot, page source or metadata.
+pub fn read_file(root: &Path, request_id: &str, id: &str, file: &str) -> io::Result<Vec<u8>> {
+    if !is_safe_segment(request_id) || !is_safe_segment(id) {
+        return Err(invalid("artifact path"));
+    }
+    if ![SCREENSHOT_FILE, HTML_FILE, META_FILE].contains(&file) {
+        return Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("no artifact file '{}'", file),
+        ));
+    }
+    std::fs::read(root.join(request_id).join(id).join(file))
+}
+
+/// Captures and saves what the browser shows because of `reason`, if capture is
+/// enabled. Failures are only logged so they never mask the scrape error itself.
+pub async fn capture_failure(
+    driver: &WebDriver,
+    config: &AppConfig,
+    request_id: &str,
+    reason: &str,
+) {
+    if config.artifact_max_per_request == 0 {
+        return;
+    }
+
+    let capture = capture(driver, &selectors::active(config), request_id, reason).await;
+    match save(
+        Path::new(&config.artifact_dir),
+        &capture,
+        config.artifact_max_per_request,
+        Duration::from_secs(config.artifact_retention_secs),
+    ) {
+        Ok(dir) => info!("Saved failure artifacts to {}", dir.display()),
+        Err(e) => error!("Failed to save failure artifacts: {}", e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WEEK: Duration = Duration::from_secs(7 * 24 * 3600);
+
+    fn sample(request_id: &str, id: &str) -> Capture {
+        Capture {
+            meta: CaptureMeta {
+                id: id.to_string(),
+                request_id: request_id.to_string(),
+                captured_at: Utc::now(),
+                error: "DOM drift: calendar has no month".to_string(),
+                url: Some("https://portal.example/calendar".to_string()),
+                page: PageState::Unknown,
+                screenshot: true,
+            },
+            html: "<html></html>".to_string(),
+            screenshot: Some(vec![0x89, b'P', b'N', b'G']),
+        }
+    }
+
+    #[test]
+    fn captures_are_saved_and_listed_newest_first() {
+        let root = tempfile::tempdir().unwrap();
+        for id in ["20261018T100000000Z", "20261018T110000000Z"] {
+            save(root.path(), &sample("req-1", id), 10, WEEK).unwrap();
+        }
+
+        let listed = list(root.path(), "req-1").unwrap();
+        let ids: Vec<&str> = listed.iter().map(|c| c.id.as_str()).collect();
+        assert_eq!(ids, vec!["20261018T110000000Z", "20261018T100000000Z"]);
+        assert_eq!(listed[0].page, PageState::Unknown);
+
+        assert_eq!(
+            read_file(root.path(), "req-1", &listed[0].id, HTML_FILE).unwrap(),
+            b"<html></html>"
+        );
+        assert_eq!(
+            read_file(root.path(), "req-1", &listed[0].id, SCREENSHOT_FILE).unwrap(),
+            vec![0x89, b'P', b'N', b'G']
+        );
+        assert!(list(root.path(), "req-2").unwrap().is_empty());
+    }
+
+    #[test]
+    fn retention_keeps_the_newest_captures() {
+        let root = tempfile::tempdir().unwrap();
+        for id in [
+            "20261018T100000000Z",
+            "20261018T110000000Z",
+            "20261018T120000000Z",
+        ] {
+            save(root.path(), &sample("req-1", id), 2, WEEK).unwrap();
+        }
+
+        let ids: Vec<String> = list(root.path(), "req-1")
+            .unwrap()
+            .into_iter()
+            .map(|c| c.id)
+            .collect();
+        assert_eq!(ids, vec!["20261018T120000000Z", "20261018T110000000Z"]);
+
+        prune(root.path(), 2, Duration::ZERO).unwrap();
+        assert!(!root.path().join("req-1").exists());
+    }
+
+    #[test]
+    fn paths_outside_the_artifact_root_are_rejected() {
+        let root = tempfile::tempdir().unwrap();
+        save(
+            root.path(),
+            &sample("req-1", "20261018T100000000Z"),
+            10,
+            WEEK,
+        )
+        .unwrap();
+
+        for (request_id, id, file) in [
+            ("..", "20261018T100000000Z", HTML_FILE),
+            ("req-1", "..", HTML_FILE),
+            ("req-1", "20261018T100000000Z", "../../etc/passwd"),
+        ] {
+            assert!(read_file(root.path(), request_id, id, file).is_err());
+        }
+        assert!(save(root.path(), &sample("../x", "1"), 10, WEEK).is_err());
+    }
+}
@@ -0,0 +1,303 @@
+use chrono::{DateTime, Utc};
+use scraper::Html;
+use serde::Serialize;
+use std::sync::Arc;
+use thirtyfour::prelude::*;
+use tracing::{error, info, warn};
+
+use crate::config::AppConfig;
+use crate::models::dmvservice::DMVService;
+use crate::scraping::error::{ScraperError, ScraperResult};
+use crate::scraping::page::PageState;
+use crate::scraping::parser::{parse_offices, selector};
+use crate::scraping::portal::{
+    AppointmentPortal, WebDriverPortal, click_service_tile, next_page, settled_page,
+};
+use crate::scraping::scraper::{spoof_location, start_chrome};
+use crate::scraping::selectors::{self, SelectorProfile};
+
+/// Where the canary tells the portal it is: downtown Raleigh.
+const CANARY_LOCATION: (f64, f64) = (35.7796, -78.6382);
+
+/// Reservable offices tried before giving up on finding a calendar.
+const MAX_OFFICES_TRIED: usize = 3;
+
+/// Pages the canary walks through, in order. It never goes past a calendar, so
+/// the time slot, contact form and captcha selectors are not checked.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CanaryStep {
+    Landing,
+    ServiceSelection,
+    OfficeList,
+    Calendar,
+}
+
+impl CanaryStep {
+    pub const ALL: [CanaryStep; 4] = [
+        CanaryStep::Landing,
+        CanaryStep::ServiceSelection,
+        CanaryStep::OfficeList,
+        CanaryStep::Calendar,
+    ];
+
+    /// The page the portal should show at this step.
+    pub fn page(self) -> PageState {
+        match self {
+            CanaryStep::Landing => PageState::Landing,
+            CanaryStep::ServiceSelection => PageState::ServiceSelection,
+            CanaryStep::OfficeList => PageState::OfficeList,
+            CanaryStep::Calendar => PageState::Calendar,
+        }
+    }
+
+    /// Selector profile fields that must match something on this step's page.
+    fn fields(self) -> &'static [&'static str] {
+        match self {
+            CanaryStep::Landing => &["make_appointment_button_id"],
+            CanaryStep::ServiceSelection => &["item_child_class"],
+            CanaryStep::OfficeList => &["office_item_class", "item_child_class"],
+            CanaryStep::Calendar => &[
+                "datepicker_class",
+                "available_date_class",
+                "back_button_id",
+                "next_button_class",
+            ],
+        }
+    }
+}
+
+/// Fields of `profile` expected at `step` that match nothing in `html`.
+pub fn unresolved(step: CanaryStep, html: &str, profile: &SelectorProfile) -> Vec<&'static str> {
+    let document = Html::parse_document(html);
+    profile
+        .css_selectors()
+        .into_iter()
+        .filter(|(field, _)| step.fields().contains(field))
+        .filter(|(_, css)| !selector(css).is_ok_and(|s| document.select(&s).next().is_some()))
+        .map(|(field, _)| field)
+        .collect()
+}
+
+/// Checks that `html` is `step`'s page and that all of its selectors resolve.
+pub fn check_page(step: CanaryStep, html: &str, profile: &SelectorProfile) -> ScraperResult<()> {
+    let missing = unresolved(step, html, profile);
+    if !missing.is_empty() {
+        return Err(ScraperError::DomDrift(format!(
+            "{} matched nothing",
+            missing.join(", ")
+        )));
+    }
+
+    let page = PageState::detect(html, profile);
+    if page != step.page() {
+        return Err(ScraperError::DomDrift(format!(
+            "expected the {:?} page, found {:?}",
+            step.page(),
+            page
+        )));
+    }
+    Ok(())
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CanaryFailure {
+    pub step: CanaryStep,
+    pub error: String,
+    /// Selector profile fields of `step` that matched nothing on the page.
+    pub missing: Vec<&'static str>,
+    /// What the page was recognised as.
+    pub page: PageState,
+    pub url: Option<String>,
+    /// Page source when the step failed; served separately by the admin API.
+    #[serde(skip)]
+    pub snapshot: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CanaryReport {
+    pub checked_at: DateTime<Utc>,
+    pub passed: bool,
+    /// Steps whose page and selectors were verified. The calendar is skipped
+    /// when no reservable office offers one, which is not a failure.
+    pub checked: Vec<CanaryStep>,
+    pub failure: Option<CanaryFailure>,
+}
+
+/// Walks the portal read-only from the landing page to one office calendar with
+/// the active selector profile.
+pub async fn run_canary(config: &AppConfig) -> ScraperResult<CanaryReport> {
+    let selectors = selectors::active(config);
+    let (driver, _profile_dir) = start_chrome(config).await?;
+
+    let mut checked = Vec::new();
+    let result = walk(&driver, &selectors, &mut checked).await;
+    let failure = match result {
+        Ok(()) => None,
+        Err(e) => {
+            // The step that failed is the first one not yet checked.
+            let step = CanaryStep::ALL[checked.len().min(CanaryStep::ALL.len() - 1)];
+            let snapshot = driver.source().await.unwrap_or_default();
+            Some(CanaryFailure {
+                step,
+                error: e.to_string(),
+                missing: unresolved(step, &snapshot, &selectors),
+                page: PageState::detect(&snapshot, &selectors),
+                url: driver.current_url().await.ok().map(|url| url.to_string()),
+                snapshot,
+            })
+        }
+    };
+
+    if let Err(e) = driver.quit().await {
+        error!("Failed to quit Chrome session: {:?}", e);
+    }
+
+    Ok(CanaryReport {
+        checked_at: Utc::now(),
+        passed: failure.is_none(),
+        checked,
+        failure,
+    })
+}
+
+async fn walk(
+    driver: &WebDriver,
+    selectors: &Arc<SelectorProfile>,
+    checked: &mut Vec<CanaryStep>,
+) -> ScraperResult<()> {
+    let service = DMVService::all()
+        .next()
+        .ok_or_else(|| ScraperError::Config("the service catalog is empty".to_string()))?;
+
+    driver.goto(&selectors.base_url).await?;
+    spoof_location(driver, selectors, CANARY_LOCATION.0, CANARY_LOCATION.1).await?;
+
+    settled_page(driver, selectors).await?;
+    check(driver, selectors, CanaryStep::Landing, checked).await?;
+    driver
+        .find(By::Id(&selectors.make_appointment_button_id))
+        .await?
+        .click()
+        .await?;
+
+    next_page(driver, selectors, &PageState::Landing).await?;
+    check(driver, selectors, CanaryStep::ServiceSelection, checked).await?;
+    click_service_tile(driver, selectors, &service).await?;
+
+    next_page(driver, selectors, &PageState::ServiceSelection).await?;
+    check(driver, selectors, CanaryStep::OfficeList, checked).await?;
+
+    let portal = WebDriverPortal::new(driver, selectors.clone());
+    let offices = parse_offices(&driver.source().await?, selectors)?;
+    for office in offices
+        .iter()
+        .filter(|office| office.is_reservable)
+        .take(MAX_OFFICES_TRIED)
+    {
+        let page = portal.open_office(&office.name).await?;
+        if page == PageState::Calendar {
+            return check(driver, selectors, CanaryStep::Calendar, checked).await;
+        }
+        portal.back_to_office_list(&page).await?;
+    }
+
+    warn!("Selector canary found no office calendar to check");
+    Ok(())
+}
+
+async fn check(
+    driver: &WebDriver,
+    selectors: &SelectorProfile,
+    step: CanaryStep,
+    checked: &mut Vec<CanaryStep>,
+) -> ScraperResult<()> {
+    check_page(step, &driver.source().await?, selectors)?;
+    info!("Selector canary: {:?} ok", step);
+    checked.push(step);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scraping::fixtures::{date, fixture};
+    use crate::scraping::mock_portal::{MockOffice, MockPortal};
+
+    #[test]
+    fn fixtures_pass_their_steps() {
+        let profile = SelectorProfile::default();
+        for (step, html) in [
+            (CanaryStep::Landing, fixture!("landing.html")),
+            (
+                CanaryStep::ServiceSelection,
+                fixture!("service_selection.html"),
+            ),
+            (CanaryStep::OfficeList, fixture!("office_list.html")),
+            (CanaryStep::Calendar, fixture!("calendar_2026_12.html")),
+        ] {
+            assert!(
+                check_page(step, html, &profile).is_ok(),
+                "{:?}: {:?}",
+                step,
+                check_page(step, html, &profile)
+            );
+        }
+    }
+
+    #[test]
+    fn renamed_selectors_are_reported() {
+        let profile = SelectorProfile {
+            back_button_id: "PreviousButton".to_string(),
+            available_date_class: "ui-state-default ui-state-open".to_string(),
+            ..SelectorProfile::default()
+        };
+        let html = fixture!("calendar_2026_12.html");
+
+        assert_eq!(
+            unresolved(CanaryStep::Calendar, html, &profile),
+            vec!["available_date_class", "back_button_id"]
+        );
+        assert!(matches!(
+            check_page(CanaryStep::Calendar, html, &profile),
+            Err(ScraperError::DomDrift(_))
+        ));
+    }
+
+    #[test]
+    fn wrong_page_fails_the_step() {
+        let result = check_page(
+            CanaryStep::Calendar,
+            fixture!("no_appointments.html"),
+            &SelectorProfile::default(),
+        );
+        assert!(matches!(result, Err(ScraperError::DomDrift(_))));
+    }
+
+    #[actix_web::test]
+    #[ignore = "needs a local chromedriver"]
+    async fn passes_on_the_mock_portal() {
+        let mock = MockPortal::start(vec![
+            MockOffice::new("Garner", "1240 US-70, Garner, NC 27529", 7.8),
+            MockOffice::new("Cary", "1387 SE Maynard Rd, Cary, NC 27511", 12.6)
+                .with_dates(&[date("2030-01-04")]),
+        ])
+        .await
+        .unwrap();
+        let config = AppConfig {
+            webdriver_url: std::env::var("WEBDRIVER_URL")
+                .unwrap_or_else(|_| "http://localhost:9515".to_string()),
+            selectors: SelectorProfile {
+                base_url: mock.portal_url(),
+                ..SelectorProfile::default()
+            },
+            ..AppConfig::default()
+        };
+
+        let report = run_canary(&config).await.unwrap();
+        mock.stop().await;
+
+        assert!(report.passed, "{:?}", report.failure);
+        assert_eq!(report.checked, CanaryStep::ALL.to_vec());
+    }
+}
@@ -17,3 +17,31 @@ pub const LNAME_INPUT_ID: &str = "StepControls_0__Model_Value_Properties_1__Valu
 pub const PHONE_NUM_INPUT_ID: &str = "StepControls_0__Model_Value_Properties_2__Value";
 pub const EMAIL_INPUT_ID: &str = "StepControls_0__Model_Value_Properties_3__Value";
 pub const CONFIRM_EMAIL_INPUT_ID: &str = "StepControls_0__Model_Value_Properties_4__Value";
+
+// Page text shown when the portal is down or overloaded
+pub const PORTAL_OUTAGE_MESSAGES: &[&str] = &[
+    "Service Unavailable",
+    "temporarily unavailable",
+    "undergoing maintenance",
+    "Server Error",
+];
+
+// Page text shown instead of a bookable calendar or time for an office
+pub const NO_APPOINTMENTS_MESSAGE: &str = "This office does not currently have any appointments available in the next 90 days. Please try scheduling an appointment at another office or try again tomorrow when a new day's appointments will be available.";
+pub const SELECT_DATE_MESSAGE: &str = "Please select a date and time to continue.";
+pub const UNABLE_MESSAGE: &str = "We were unable";
+pub const OFFICE_UNAVAILABLE_MESSAGES: &[&str] =
+    &[NO_APPOINTMENTS_MESSAGE, SELECT_DATE_MESSAGE, UNABLE_MESSAGE];
+
+// Navigation controls of the booking wizard
+pub const BACK_BUTTON_ID: &str = "BackButton";
+pub const NEXT_BUTTON_CLASS: &str = "next-button";
+pub const DATEPICKER_CLASS: &str = "ui-datepicker-calendar";
+pub const TIME_SLOT_CLASS: &str = "AppointmentTime";
+pub const RECAPTCHA_RESPONSE_ID: &str = "g-recaptcha-response";
+
+// Page text shown once an appointment is booked
+pub const CONFIRMATION_MESSAGES: &[&str] = &[
+    "Your appointment has been scheduled",
+    "Appointment Confirmation",
+];
@@ -0,0 +1,233 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::time::Duration;
+use thirtyfour::prelude::*;
+use tracing::{error, info};
+
+use crate::config::AppConfig;
+use crate::models::dmvservice::DMVService;
+use crate::scraping::error::ScraperResult;
+use crate::scraping::scraper::start_chrome;
+use crate::scraping::selectors::{self, SelectorProfile, class_css};
+use crate::scraping::wait::{PAGE_TIMEOUT, POLL_INTERVAL, wait_for};
+
+/// How long to wait for the service tiles after clicking "Make an Appointment".
+const TILE_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// A catalog service whose selector no longer matches, but whose title still
+/// appears on a tile.
+#[derive(Debug, Clone, Serialize)]
+pub struct RenamedService {
+    pub slug: String,
+    pub selector: String,
+    pub tile: String,
+}
+
+/// Differences between the portal's service tiles and our catalog.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ServiceDiff {
+    /// Tiles that match no catalog service.
+    pub added: Vec<String>,
+    /// Slugs of catalog services with no tile.
+    pub removed: Vec<String>,
+    pub renamed: Vec<RenamedService>,
+}
+
+impl ServiceDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.renamed.is_empty()
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DiscoveryReport {
+    pub checked_at: DateTime<Utc>,
+    /// Text of every tile on the service selection page.
+    pub tiles: Vec<String>,
+    pub diff: ServiceDiff,
+}
+
+/// Matches tiles to catalog services by selector, falling back to the title to
+/// recognise a service whose description text changed.
+pub fn diff_catalog(tiles: &[String]) -> ServiceDiff {
+    let mut diff = ServiceDiff::default();
+    let mut matched = vec![false; tiles.len()];
+
+    for service in DMVService::all() {
+        if let Some(i) = tiles
+            .iter()
+            .enumerate()
+            .position(|(i, tile)| !matched[i] && tile.contains(service.selector()))
+        {
+            matched[i] = true;
+            continue;
+        }
+
+        match tiles
+            .iter()
+            .enumerate()
+            .find(|(i, tile)| !matched[*i] && tile_title(tile) == service.title())
+        {
+            Some((i, tile)) => {
+                matched[i] = true;
+                diff.renamed.push(RenamedService {
+                    slug: service.slug().to_string(),
+                    selector: service.selector().to_string(),
+                    tile: tile.clone(),
+                });
+            }
+            None => diff.removed.push(service.slug().to_string()),
+        }
+    }
+
+    diff.added = tiles
+        .iter()
+        .zip(matched)
+        .filter(|(_, matched)| !matched)
+        .map(|(tile, _)| tile.clone())
+        .collect();
+
+    diff
+}
+
+/// First line of a tile, which holds the service title.
+fn tile_title(tile: &str) -> &str {
+    tile.lines().next().unwrap_or_default().trim()
+}
+
+/// Opens the service selection page and compares its tiles with the catalog.
+pub async fn discover_services(config: &AppConfig) -> ScraperResult<DiscoveryReport> {
+    let (driver, _profile_dir) = start_chrome(config).await?;
+
+    let result = read_tiles(&driver, &selectors::active(config)).await;
+
+    if let Err(e) = driver.quit().await {
+        error!("Failed to quit Chrome session: {:?}", e);
+    }
+
+    let tiles = result?;
+    let diff = diff_catalog(&tiles);
+    info!(
+        "Discovered {} service tiles on the portal ({} added, {} removed, {} renamed)",
+        tiles.len(),
+        diff.added.len(),
+        diff.removed.len(),
+        diff.renamed.len()
+    );
+
+    Ok(DiscoveryReport {
+        checked_at: Utc::now(),
+        tiles,
+        diff,
+    })
+}
+
+async fn read_tiles(driver: &WebDriver, selectors: &SelectorProfile) -> ScraperResult<Vec<String>> {
+    driver.goto(&selectors.base_url).await?;
+    let tile_css = &format!("div{}", class_css(&selectors.item_child_class));
+    wait_for(
+        "the Make an Appointment button",
+        PAGE_TIMEOUT,
+        POLL_INTERVAL,
+        || async move {
+            Ok(Some(
+                driver
+                    .find(By::Id(&selectors.make_appointment_button_id))
+                    .await?,
+            ))
+        },
+    )
+    .await?
+    .click()
+    .await?;
+
+    wait_for(
+        "the service selection tiles",
+        TILE_TIMEOUT,
+        POLL_INTERVAL,
+        || async move {
+            let elements = driver.find_all(By::Css(tile_css)).await?;
+            let mut tiles = Vec::new();
+            for element in elements {
+                let text = element.text().await?.trim().to_string();
+                if !text.is_empty() {
+                    tiles.push(text);
+                }
+            }
+            Ok((!tiles.is_empty()).then_some(tiles))
+        },
+    )
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scraping::fixtures::fixture;
+    use crate::scraping::parser::selector;
+    use scraper::Html;
+
+    /// Tile texts as WebDriver renders them: one line per block inside the tile.
+    fn tiles(html: &str) -> Vec<String> {
+        let document = Html::parse_document(html);
+        let tile = selector("div.form-control-child").unwrap();
+        let line = selector("div").unwrap();
+        document
+            .select(&tile)
+            .map(|tile| {
+                tile.select(&line)
+                    .map(|line| line.text().collect::<String>().trim().to_string())
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            })
+            .collect()
+    }
+
+    #[test]
+    fn fixture_tiles_match_the_catalog() {
+        let tiles = tiles(fixture!("service_selection.html"));
+        assert_eq!(tiles.len(), DMVService::all().count());
+        assert_eq!(tile_title(&tiles[0]), "Driver License - First Time");
+
+        let diff = diff_catalog(&tiles);
+        assert!(diff.is_empty(), "{:?}", diff);
+    }
+
+    #[test]
+    fn changed_tiles_are_reported() {
+        let mut tiles = tiles(fixture!("service_selection.html"));
+        // Renewal keeps its title but gets a new description.
+        let renewal = tiles
+            .iter()
+            .position(|tile| tile_title(tile) == "Driver License Renewal")
+            .unwrap();
+        tiles[renewal] = "Driver License Renewal\nRenew your license before it expires".to_string();
+        tiles.retain(|tile| tile_title(tile) != "Fees");
+        tiles.push("Boat License\nOperate a vessel on NC waters".to_string());
+
+        let diff = diff_catalog(&tiles);
+        assert_eq!(
+            diff.added,
+            vec!["Boat License\nOperate a vessel on NC waters"]
+        );
+        assert_eq!(diff.removed, vec!["fees"]);
+        assert_eq!(diff.renamed.len(), 1);
+        assert_eq!(diff.renamed[0].slug, "renewal");
+        assert_eq!(diff.renamed[0].tile, tiles[renewal]);
+    }
+
+    #[test]
+    fn a_tile_is_claimed_by_one_service_only() {
+        // Both the motorcycle and the road test selectors are in this tile.
+        let tiles = vec![
+            "Road Tests\nSchedule a driving skills test or Schedule a motorcycle driving skills test"
+                .to_string(),
+        ];
+
+        let diff = diff_catalog(&tiles);
+        assert!(diff.added.is_empty());
+        // The motorcycle test comes first in the catalog and claims it.
+        assert!(!diff.removed.contains(&"motorcycle-test".to_string()));
+        assert!(diff.removed.contains(&"non-cdl-road-test".to_string()));
+    }
+}
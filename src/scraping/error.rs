@@ -0,0 +1,96 @@
+use std::error::Error;
+use std::fmt;
+use thirtyfour::error::{WebDriverError, WebDriverErrorInner};
+
+/// What a listener should do after a scraper error.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Recovery {
+    /// Try again on the next sweep.
+    Retry,
+    /// Wait before trying again; the portal or a dependency is struggling.
+    BackOff,
+    /// Give up and mark the request failed.
+    Fail,
+}
+
+/// Everything that can go wrong while scraping or booking on the portal.
+#[derive(Debug)]
+pub enum ScraperError {
+    /// An element or text the scraper relies on is missing or has an unexpected shape.
+    DomDrift(String),
+    /// A page did not reach the expected state in time.
+    NavigationTimeout(String),
+    /// Solving or submitting the reCAPTCHA failed.
+    Captcha(String),
+    /// The proxy email service could not register an address.
+    EmailProxy(String),
+    /// The portal showed an outage or maintenance message.
+    PortalOutage(String),
+    /// A setting or input the scraper needs is missing or invalid.
+    Config(String),
+    /// The Chrome session died or could not be created.
+    SessionLost(WebDriverError),
+    /// Any other WebDriver failure.
+    WebDriver(WebDriverError),
+}
+
+impl ScraperError {
+    pub fn recovery(&self) -> Recovery {
+        match self {
+            ScraperError::DomDrift(_) | ScraperError::WebDriver(_) => Recovery::Retry,
+            ScraperError::NavigationTimeout(_)
+            | ScraperError::Captcha(_)
+            | ScraperError::EmailProxy(_)
+            | ScraperError::PortalOutage(_)
+            | ScraperError::SessionLost(_) => Recovery::BackOff,
+            ScraperError::Config(_) => Recovery::Fail,
+        }
+    }
+
+    /// Whether the Chrome session can no longer be used and must be restarted.
+    pub fn ends_session(&self) -> bool {
+        matches!(self, ScraperError::SessionLost(_) | ScraperError::Config(_))
+    }
+}
+
+impl fmt::Display for ScraperError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ScraperError::DomDrift(message) => write!(f, "Portal markup changed: {}", message),
+            ScraperError::NavigationTimeout(message) => {
+                write!(f, "Timed out waiting for {}", message)
+            }
+            ScraperError::Captcha(message) => write!(f, "Captcha failed: {}", message),
+            ScraperError::EmailProxy(message) => write!(f, "Email proxy failed: {}", message),
+            ScraperError::PortalOutage(message) => write!(f, "Portal outage: {}", message),
+            ScraperError::Config(message) => write!(f, "Configuration error: {}", message),
+            ScraperError::SessionLost(e) => write!(f, "Chrome session lost: {}", e),
+            ScraperError::WebDriver(e) => write!(f, "WebDriver error: {}", e),
+        }
+    }
+}
+
+impl Error for ScraperError {}
+
+impl From<WebDriverError> for ScraperError {
+    fn from(e: WebDriverError) -> Self {
+        match e.as_inner() {
+            WebDriverErrorInner::NoSuchElement(_)
+            | WebDriverErrorInner::StaleElementReference(_) => {
+                ScraperError::DomDrift(e.to_string())
+            }
+            WebDriverErrorInner::Timeout(_)
+            | WebDriverErrorInner::WebDriverTimeout(_)
+            | WebDriverErrorInner::ScriptTimeout(_) => {
+                ScraperError::NavigationTimeout(e.to_string())
+            }
+            WebDriverErrorInner::InvalidSessionId(_)
+            | WebDriverErrorInner::NoSuchWindow(_)
+            | WebDriverErrorInner::SessionNotCreated(_)
+            | WebDriverErrorInner::RequestFailed(_) => ScraperError::SessionLost(e),
+            _ => ScraperError::WebDriver(e),
+        }
+    }
+}
+
+pub type ScraperResult<T> = Result<T, ScraperError>;
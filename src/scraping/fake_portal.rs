@@ -0,0 +1,246 @@
+use async_trait::async_trait;
+use chrono::NaiveDate;
+use std::sync::Mutex;
+
+use crate::models::dmvservice::DMVService;
+use crate::scraping::error::{ScraperError, ScraperResult};
+use crate::scraping::page::PageState;
+use crate::scraping::portal::{AppointmentPortal, BookingServices, ContactDetails, PortalOffice};
+
+/// An office on the fake portal and what it shows once opened.
+#[derive(Debug, Clone)]
+pub struct ScriptedOffice {
+    pub office: PortalOffice,
+    /// Page shown after clicking the office; normally the calendar.
+    pub opens_to: PageState,
+    pub dates: Vec<NaiveDate>,
+    /// Whether picking a date leads to time slots before the contact form.
+    pub time_slots: bool,
+    /// Page shown after the contact form is submitted.
+    pub submits_to: PageState,
+}
+
+impl ScriptedOffice {
+    /// A reservable office offering `dates` that confirms any booking.
+    pub fn reservable(name: &str, distance: u16, dates: &[NaiveDate]) -> Self {
+        ScriptedOffice {
+            office: PortalOffice {
+                name: name.to_string(),
+                street_address: "1 Test St, Raleigh, NC".to_string(),
+                zip_code: "27601".to_string(),
+                distance,
+                is_reservable: true,
+            },
+            opens_to: PageState::Calendar,
+            dates: dates.to_vec(),
+            time_slots: false,
+            submits_to: PageState::Confirmation,
+        }
+    }
+
+    /// An office the portal greys out.
+    pub fn closed(name: &str, distance: u16) -> Self {
+        let mut office = Self::reservable(name, distance, &[]);
+        office.office.is_reservable = false;
+        office
+    }
+
+    pub fn opens_to(mut self, page: PageState) -> Self {
+        self.opens_to = page;
+        self
+    }
+
+    pub fn with_time_slots(mut self) -> Self {
+        self.time_slots = true;
+        self
+    }
+
+    pub fn submits_to(mut self, page: PageState) -> Self {
+        self.submits_to = page;
+        self
+    }
+}
+
+#[derive(Debug)]
+struct FakeState {
+    page: PageState,
+    open: Option<usize>,
+    calls: Vec<String>,
+    captures: Vec<String>,
+}
+
+/// An `AppointmentPortal` that plays back scripted offices and records every call.
+/// Calls made on the wrong page fail with `DomDrift`, like the real portal would.
+pub struct FakePortal {
+    offices: Vec<ScriptedOffice>,
+    state: Mutex<FakeState>,
+}
+
+impl FakePortal {
+    pub fn new(offices: Vec<ScriptedOffice>) -> Self {
+        FakePortal {
+            offices,
+            state: Mutex::new(FakeState {
+                page: PageState::Landing,
+                open: None,
+                calls: Vec::new(),
+                captures: Vec::new(),
+            }),
+        }
+    }
+
+    /// Calls made so far, e.g. `"select_date 2030-01-02"`.
+    pub fn calls(&self) -> Vec<String> {
+        self.state.lock().unwrap().calls.clone()
+    }
+
+    /// Reasons passed to `capture`, kept apart from `calls` so they don't
+    /// interleave with the wizard steps.
+    pub fn captures(&self) -> Vec<String> {
+        self.state.lock().unwrap().captures.clone()
+    }
+
+    pub fn page(&self) -> PageState {
+        self.state.lock().unwrap().page.clone()
+    }
+
+    /// Records `call` and checks that the browser is on `expected`.
+    fn step(&self, call: String, expected: &[PageState]) -> ScraperResult<ScriptedOffice> {
+        let mut state = self.state.lock().unwrap();
+        state.calls.push(call.clone());
+        if !expected.contains(&state.page) {
+            return Err(ScraperError::DomDrift(format!(
+                "{} called on {:?}",
+                call, state.page
+            )));
+        }
+        state
+            .open
+            .map(|index| self.offices[index].clone())
+            .ok_or_else(|| ScraperError::DomDrift(format!("{} called with no office open", call)))
+    }
+
+    fn go_to(&self, page: PageState) -> PageState {
+        self.state.lock().unwrap().page = page.clone();
+        page
+    }
+}
+
+#[async_trait]
+impl AppointmentPortal for FakePortal {
+    async fn list_offices(&self, service: &DMVService) -> ScraperResult<Vec<PortalOffice>> {
+        let mut state = self.state.lock().unwrap();
+        state.calls.push(format!("list_offices {}", service.slug()));
+        state.page = PageState::OfficeList;
+        state.open = None;
+        Ok(self.offices.iter().map(|o| o.office.clone()).collect())
+    }
+
+    async fn open_office(&self, office_name: &str) -> ScraperResult<PageState> {
+        let mut state = self.state.lock().unwrap();
+        state.calls.push(format!("open_office {}", office_name));
+        if state.page != PageState::OfficeList {
+            return Err(ScraperError::DomDrift(format!(
+                "open_office called on {:?}",
+                state.page
+            )));
+        }
+        let index = self
+            .offices
+            .iter()
+            .position(|o| o.office.name == office_name)
+            .ok_or_else(|| ScraperError::DomDrift(format!("no office '{}'", office_name)))?;
+        state.open = Some(index);
+        state.page = self.offices[index].opens_to.clone();
+        Ok(state.page.clone())
+    }
+
+    async fn available_dates(&self) -> ScraperResult<Vec<NaiveDate>> {
+        let office = self.step("available_dates".to_string(), &[PageState::Calendar])?;
+        Ok(office.dates)
+    }
+
+    async fn select_date(&self, date: NaiveDate) -> ScraperResult<PageState> {
+        let office = self.step(format!("select_date {}", date), &[PageState::Calendar])?;
+        if !office.dates.contains(&date) {
+            return Err(ScraperError::DomDrift(format!("{} is not offered", date)));
+        }
+        Ok(self.go_to(if office.time_slots {
+            PageState::TimeSelection
+        } else {
+            PageState::ContactForm
+        }))
+    }
+
+    async fn select_time(&self) -> ScraperResult<PageState> {
+        self.step("select_time".to_string(), &[PageState::TimeSelection])?;
+        Ok(self.go_to(PageState::ContactForm))
+    }
+
+    async fn back_to_office_list(&self, from: &PageState) -> ScraperResult<()> {
+        self.step(
+            "back_to_office_list".to_string(),
+            std::slice::from_ref(from),
+        )?;
+        self.go_to(PageState::OfficeList);
+        Ok(())
+    }
+
+    async fn fill_contact_form(&self, contact: &ContactDetails) -> ScraperResult<()> {
+        self.step(
+            format!("fill_contact_form {}", contact.email),
+            &[PageState::ContactForm],
+        )?;
+        Ok(())
+    }
+
+    async fn submit_captcha_token(&self, token: &str) -> ScraperResult<()> {
+        let office = self.step(
+            format!("submit_captcha_token {}", token),
+            &[PageState::ContactForm],
+        )?;
+        self.go_to(office.submits_to);
+        Ok(())
+    }
+
+    async fn read_confirmation(&self) -> ScraperResult<PageState> {
+        let page = self.page();
+        self.step("read_confirmation".to_string(), std::slice::from_ref(&page))?;
+        Ok(page)
+    }
+
+    async fn capture(&self, reason: &str) {
+        self.state.lock().unwrap().captures.push(reason.to_string());
+    }
+}
+
+/// Fixed contact details and captcha token, without any network calls.
+#[derive(Debug, Default)]
+pub struct FakeBookingServices {
+    calls: Mutex<Vec<&'static str>>,
+}
+
+impl FakeBookingServices {
+    /// Services requested so far, e.g. `"captcha_token"`.
+    pub fn calls(&self) -> Vec<&'static str> {
+        self.calls.lock().unwrap().clone()
+    }
+}
+
+#[async_trait]
+impl BookingServices for FakeBookingServices {
+    async fn contact_details(&self, _dates: &[String]) -> ScraperResult<ContactDetails> {
+        self.calls.lock().unwrap().push("contact_details");
+        Ok(ContactDetails {
+            first_name: "Test".to_string(),
+            last_name: "User".to_string(),
+            phone_number: "9195550100".to_string(),
+            email: "proxy@example.com".to_string(),
+        })
+    }
+
+    async fn captcha_token(&self) -> ScraperResult<String> {
+        self.calls.lock().unwrap().push("captcha_token");
+        Ok("fake-token".to_string())
+    }
+}
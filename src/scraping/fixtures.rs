@@ -0,0 +1,18 @@
+use chrono::NaiveDate;
+
+/// A saved portal page from `tests/fixtures/portal`, embedded at compile time.
+macro_rules! fixture {
+    ($name:literal) => {
+        include_str!(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/tests/fixtures/portal/",
+            $name
+        ))
+    };
+}
+pub(crate) use fixture;
+
+/// Parses a `YYYY-MM-DD` date, panicking on typos in the test itself.
+pub fn date(s: &str) -> NaiveDate {
+    NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap()
+}
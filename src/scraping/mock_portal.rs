@@ -0,0 +1,551 @@
+//! A local imitation of the NC DMV appointment portal, so the scraper can be driven
+//! end to end with a local chromedriver. It also answers the email proxy's
+//! `/register` call.
+
+use actix_web::dev::ServerHandle;
+use actix_web::{App, HttpResponse, HttpServer, web};
+use chrono::{Datelike, NaiveDate};
+use serde::Deserialize;
+use serde_json::json;
+use std::sync::Mutex;
+
+use crate::models::dmvservice::DMVService;
+use crate::scraping::constants::*;
+
+/// Path of the landing page, shaped like the real portal's.
+pub const INDEX_PATH: &str = "/Webapp/Appointment/Index/mock";
+
+/// An office on the mock portal and its scripted availability.
+#[derive(Debug, Clone)]
+pub struct MockOffice {
+    pub name: String,
+    pub address: String,
+    pub distance: f32,
+    pub reservable: bool,
+    pub dates: Vec<NaiveDate>,
+    /// Time slots offered after picking a date; none skips the time step.
+    pub times: Vec<String>,
+    /// Show "We were unable" instead of confirming bookings.
+    pub refuses_bookings: bool,
+}
+
+impl MockOffice {
+    /// A greyed-out office with no dates.
+    pub fn new(name: &str, address: &str, distance: f32) -> Self {
+        MockOffice {
+            name: name.to_string(),
+            address: address.to_string(),
+            distance,
+            reservable: false,
+            dates: Vec::new(),
+            times: Vec::new(),
+            refuses_bookings: false,
+        }
+    }
+
+    /// Makes the office reservable on `dates`, which must share a month.
+    pub fn with_dates(mut self, dates: &[NaiveDate]) -> Self {
+        self.reservable = true;
+        self.dates = dates.to_vec();
+        self
+    }
+
+    pub fn with_times(mut self, times: &[&str]) -> Self {
+        self.times = times.iter().map(|t| t.to_string()).collect();
+        self
+    }
+
+    pub fn refusing_bookings(mut self) -> Self {
+        self.refuses_bookings = true;
+        self
+    }
+}
+
+/// A contact form submitted to the mock portal.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct MockBooking {
+    pub office: String,
+    pub date: NaiveDate,
+    #[serde(default)]
+    pub time: String,
+    pub first_name: String,
+    pub last_name: String,
+    pub phone_number: String,
+    pub email: String,
+    pub confirm_email: String,
+    #[serde(rename = "g-recaptcha-response")]
+    pub captcha_token: String,
+}
+
+#[derive(Default)]
+pub struct MockState {
+    offices: Mutex<Vec<MockOffice>>,
+    bookings: Mutex<Vec<MockBooking>>,
+    /// Real addresses registered with the mock email proxy.
+    registered_emails: Mutex<Vec<String>>,
+}
+
+impl MockState {
+    pub fn new(offices: Vec<MockOffice>) -> Self {
+        MockState {
+            offices: Mutex::new(offices),
+            ..Default::default()
+        }
+    }
+
+    /// Replaces the offices, e.g. to open a slot between sweeps.
+    pub fn set_offices(&self, offices: Vec<MockOffice>) {
+        *self.offices.lock().unwrap() = offices;
+    }
+
+    pub fn bookings(&self) -> Vec<MockBooking> {
+        self.bookings.lock().unwrap().clone()
+    }
+
+    pub fn registered_emails(&self) -> Vec<String> {
+        self.registered_emails.lock().unwrap().clone()
+    }
+
+    fn office(&self, index: usize) -> Option<MockOffice> {
+        self.offices.lock().unwrap().get(index).cloned()
+    }
+}
+
+/// A running mock portal on a random local port.
+pub struct MockPortal {
+    pub state: web::Data<MockState>,
+    base_url: String,
+    handle: ServerHandle,
+}
+
+impl MockPortal {
+    pub async fn start(offices: Vec<MockOffice>) -> std::io::Result<MockPortal> {
+        let state = web::Data::new(MockState::new(offices));
+        let app_state = state.clone();
+        let server =
+            HttpServer::new(move || App::new().app_data(app_state.clone()).configure(configure))
+                .workers(1)
+                .bind(("127.0.0.1", 0))?;
+
+        let base_url = format!("http://{}", server.addrs()[0]);
+        let server = server.run();
+        let handle = server.handle();
+        actix_web::rt::spawn(server);
+
+        Ok(MockPortal {
+            state,
+            base_url,
+            handle,
+        })
+    }
+
+    /// Origin of the mock, used as the email proxy URL.
+    pub fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    /// The landing page, used as the portal URL.
+    pub fn portal_url(&self) -> String {
+        format!("{}{}", self.base_url, INDEX_PATH)
+    }
+
+    pub async fn stop(self) {
+        self.handle.stop(true).await;
+    }
+}
+
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    cfg.route(INDEX_PATH, web::get().to(landing))
+        .route("/services", web::get().to(services))
+        .route("/offices", web::get().to(offices))
+        .route("/calendar/{office}", web::get().to(calendar))
+        .route("/contact", web::get().to(contact))
+        .route("/book", web::post().to(book))
+        .route("/register", web::post().to(register));
+}
+
+fn page(title: &str, body: &str) -> HttpResponse {
+    HttpResponse::Ok().content_type("text/html").body(format!(
+        r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+  <meta charset="utf-8">
+  <title>Appointment - NCDOT</title>
+</head>
+<body>
+  <div class="step-title">{title}</div>
+  {body}
+</body>
+</html>"#
+    ))
+}
+
+fn back_button() -> String {
+    format!(
+        r#"<input type="button" id="{BACK_BUTTON_ID}" value="Back" onclick="location.href='/offices'">"#
+    )
+}
+
+async fn landing() -> HttpResponse {
+    page(
+        "NCDMV Appointments",
+        &format!(
+            r#"<input type="button" id="{BUTTON_MAKE_APPT_ID}" class="btn btn-primary" value="Make an Appointment" onclick="location.href='/services'">"#
+        ),
+    )
+}
+
+async fn services() -> HttpResponse {
+    let tiles: String = DMVService::all()
+        .map(|service| {
+            format!(
+                r#"<div class="{DMV_CHILD_CLASS}" onclick="location.href='/offices?service={}'">{}<div class="tile-description">{}</div></div>"#,
+                service.slug(),
+                service.title(),
+                service.selector()
+            )
+        })
+        .collect();
+    page(
+        "Select a Service",
+        &format!(r#"<div class="QflowObjectItems">{tiles}</div>"#),
+    )
+}
+
+async fn offices(state: web::Data<MockState>) -> HttpResponse {
+    let items: String = state
+        .offices
+        .lock()
+        .unwrap()
+        .iter()
+        .enumerate()
+        .map(|(index, office)| {
+            let unit = if office.reservable {
+                ACTIVE_UNIT_CLASS
+            } else {
+                "disabled-unit"
+            };
+            format!(
+                r#"<div class="{DMV_ITEM_CLASS} form-control ui-selectable {unit}" data-id="{index}" onclick="location.href='/calendar/{index}'">
+  <div>
+    <div>{}</div>
+    <div class="{DMV_CHILD_CLASS}">{}</div>
+  </div>
+  <div class="mt-2">{} Miles</div>
+</div>"#,
+                office.name, office.address, office.distance
+            )
+        })
+        .collect();
+    page(
+        "Select a Location",
+        &format!(
+            r#"<div class="QflowObjectItems">{items}</div><div class="StepControls">{}</div>"#,
+            back_button()
+        ),
+    )
+}
+
+async fn calendar(state: web::Data<MockState>, office: web::Path<usize>) -> HttpResponse {
+    let index = office.into_inner();
+    let Some(office) = state.office(index) else {
+        return HttpResponse::NotFound().finish();
+    };
+    let Some(first) = office.dates.iter().min().copied() else {
+        return page(
+            "Select a Date",
+            &format!(
+                r#"<div class="alert alert-warning" role="alert">{NO_APPOINTMENTS_MESSAGE}</div><div class="StepControls">{}</div>"#,
+                back_button()
+            ),
+        );
+    };
+
+    let days_in_month = (28..=31)
+        .rev()
+        .find(|day| NaiveDate::from_ymd_opt(first.year(), first.month(), *day).is_some())
+        .unwrap_or(28);
+    let mut cells = Vec::new();
+    for _ in 0..first.with_day(1).unwrap().weekday().num_days_from_sunday() {
+        cells.push(r#"<td class="ui-datepicker-other-month ui-datepicker-unselectable ui-state-disabled">&#xa0;</td>"#.to_string());
+    }
+    for day in 1..=days_in_month {
+        let date = first.with_day(day).unwrap();
+        cells.push(if office.dates.contains(&date) {
+            format!(
+                r##"<td data-handler="selectDay" data-event="click" data-month="{}" data-year="{}"><a class="{AVAILABLE_DATE_CLASS}" href="#" onclick="pickDate('{date}'); return false;">{day}</a></td>"##,
+                date.month0(),
+                date.year()
+            )
+        } else {
+            format!(
+                r#"<td class="ui-datepicker-unselectable ui-state-disabled"><span class="ui-state-default">{day}</span></td>"#
+            )
+        });
+    }
+    let rows: String = cells
+        .chunks(7)
+        .map(|week| format!("<tr>{}</tr>", week.concat()))
+        .collect();
+
+    let body = format!(
+        r#"<form id="form0" action="/contact" method="get">
+  <div id="messages" class="validation-summary-errors text-danger" role="alert"></div>
+  <input type="hidden" name="office" value="{index}">
+  <input type="hidden" id="date" name="date" value="">
+  <input type="hidden" id="time" name="time" value="">
+  <div id="datepicker" class="hasDatepicker">
+    <div class="ui-datepicker-inline ui-datepicker ui-widget ui-widget-content ui-helper-clearfix ui-corner-all">
+      <div class="ui-datepicker-header ui-widget-header ui-helper-clearfix ui-corner-all">
+        <div class="ui-datepicker-title"><span class="ui-datepicker-month">{month}</span>&nbsp;<span class="ui-datepicker-year">{year}</span></div>
+      </div>
+      <table class="{DATEPICKER_CLASS}"><tbody>{rows}</tbody></table>
+    </div>
+  </div>
+  <div id="times" class="AppointmentTimes"></div>
+  <div class="StepControls">{back}<input type="button" class="{NEXT_BUTTON_CLASS}" value="Next" onclick="next()"></div>
+</form>
+<script>
+  var times = {times};
+  function pickDate(date) {{
+    document.getElementById('date').value = date;
+    document.getElementById('times').innerHTML = times.map(function (t) {{
+      return '<div class="{TIME_SLOT_CLASS} form-control ui-selectable" onclick="pickTime(this)">' + t + '</div>';
+    }}).join('');
+  }}
+  function pickTime(slot) {{
+    document.getElementById('time').value = slot.textContent;
+  }}
+  function next() {{
+    if (!document.getElementById('date').value || (times.length && !document.getElementById('time').value)) {{
+      document.getElementById('messages').textContent = '{select_date_start}' + '{select_date_end}';
+      return;
+    }}
+    document.getElementById('form0').submit();
+  }}
+</script>"#,
+        month = first.format("%B"),
+        year = first.year(),
+        back = back_button(),
+        times = json!(office.times),
+        // Split so the script itself doesn't read as the message.
+        select_date_start = &SELECT_DATE_MESSAGE[..10],
+        select_date_end = &SELECT_DATE_MESSAGE[10..],
+    );
+    page("Select a Date", &body)
+}
+
+#[derive(Deserialize)]
+struct ContactQuery {
+    office: usize,
+    date: NaiveDate,
+    #[serde(default)]
+    time: String,
+}
+
+async fn contact(state: web::Data<MockState>, query: web::Query<ContactQuery>) -> HttpResponse {
+    let Some(office) = state.office(query.office) else {
+        return HttpResponse::NotFound().finish();
+    };
+    let field = |id: &str, name: &str, label: &str| {
+        format!(
+            r#"<div class="form-group"><label for="{id}">{label}</label><input class="form-control" id="{id}" name="{name}" type="text" value=""></div>"#
+        )
+    };
+    let body = format!(
+        r#"<form id="form0" action="/book" method="post">
+  <input type="hidden" name="office" value="{office}">
+  <input type="hidden" name="date" value="{date}">
+  <input type="hidden" name="time" value="{time}">
+  {first}{last}{phone}{email}{confirm}
+  <div class="g-recaptcha" data-sitekey="mock" data-callback="CaptchaCallBack"></div>
+  <textarea id="{RECAPTCHA_RESPONSE_ID}" name="g-recaptcha-response" style="display: none;"></textarea>
+  <div class="StepControls">{back}<input type="button" class="{NEXT_BUTTON_CLASS}" value="Next" disabled onclick="document.getElementById('form0').submit()"></div>
+</form>
+<script>
+  function CaptchaCallBack(token) {{
+    document.getElementById('{RECAPTCHA_RESPONSE_ID}').value = token;
+    document.querySelector('.{NEXT_BUTTON_CLASS}').disabled = false;
+  }}
+</script>"#,
+        office = office.name,
+        date = query.date,
+        time = query.time,
+        first = field(FNAME_INPUT_ID, "first_name", "First Name"),
+        last = field(LNAME_INPUT_ID, "last_name", "Last Name"),
+        phone = field(PHONE_NUM_INPUT_ID, "phone_number", "Phone Number"),
+        email = field(EMAIL_INPUT_ID, "email", "Email"),
+        confirm = field(CONFIRM_EMAIL_INPUT_ID, "confirm_email", "Confirm Email"),
+        back = back_button(),
+    );
+    page("Contact Information", &body)
+}
+
+async fn book(state: web::Data<MockState>, form: web::Form<MockBooking>) -> HttpResponse {
+    let booking = form.into_inner();
+    let refuses = state
+        .offices
+        .lock()
+        .unwrap()
+        .iter()
+        .any(|office| office.name == booking.office && office.refuses_bookings);
+    if refuses {
+        return page(
+            "Appointment Details",
+            &format!(
+                r#"<div class="validation-summary-errors text-danger" role="alert">{UNABLE_MESSAGE} to schedule your appointment because the selected time is no longer available.</div><div class="StepControls">{}</div>"#,
+                back_button()
+            ),
+        );
+    }
+
+    let body = format!(
+        r#"<div class="confirmation"><p>{}.</p><dl><dt>Location</dt><dd>{}</dd><dt>Date</dt><dd>{} {}</dd></dl></div>"#,
+        CONFIRMATION_MESSAGES[0], booking.office, booking.date, booking.time
+    );
+    state.bookings.lock().unwrap().push(booking);
+    page("Appointment Confirmation", &body)
+}
+
+#[derive(Deserialize)]
+struct RegisterBody {
+    real_email: String,
+}
+
+async fn register(state: web::Data<MockState>, body: web::Json<RegisterBody>) -> HttpResponse {
+    let mut registered = state.registered_emails.lock().unwrap();
+    registered.push(body.into_inner().real_email);
+    HttpResponse::Ok().json(json!({
+        "proxy_email": format!("proxy-{}@mock.test", registered.len())
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scraping::fixtures::date;
+    use crate::scraping::page::PageState;
+    use crate::scraping::parser::{parse_calendar, parse_offices};
+    use crate::scraping::selectors::SelectorProfile;
+    use actix_web::body::to_bytes;
+    use actix_web::test;
+
+    fn offices() -> Vec<MockOffice> {
+        vec![
+            MockOffice::new("Garner", "1240 US-70, Garner, NC 27529", 7.8),
+            MockOffice::new("Raleigh West", "4121 New Bern Ave, Raleigh, NC 27610", 3.2)
+                .with_dates(&[date("2030-01-04"), date("2030-01-29")])
+                .with_times(&["8:00 AM", "8:15 AM"]),
+            MockOffice::new("Cary", "1387 SE Maynard Rd, Cary, NC 27511", 12.5)
+                .with_dates(&[date("2030-02-01")])
+                .refusing_bookings(),
+        ]
+    }
+
+    async fn get(state: &web::Data<MockState>, uri: &str) -> String {
+        let app = test::init_service(App::new().app_data(state.clone()).configure(configure)).await;
+        let response =
+            test::call_service(&app, test::TestRequest::get().uri(uri).to_request()).await;
+        assert!(response.status().is_success(), "GET {}", uri);
+        String::from_utf8(to_bytes(response.into_body()).await.unwrap().to_vec()).unwrap()
+    }
+
+    async fn post_booking(state: &web::Data<MockState>, office: &str) -> String {
+        let app = test::init_service(App::new().app_data(state.clone()).configure(configure)).await;
+        let request = test::TestRequest::post()
+            .uri("/book")
+            .set_form([
+                ("office", office),
+                ("date", "2030-01-04"),
+                ("time", "8:00 AM"),
+                ("first_name", "Test"),
+                ("last_name", "User"),
+                ("phone_number", "9195550100"),
+                ("email", "proxy-1@mock.test"),
+                ("confirm_email", "proxy-1@mock.test"),
+                ("g-recaptcha-response", "token"),
+            ])
+            .to_request();
+        let response = test::call_service(&app, request).await;
+        String::from_utf8(to_bytes(response.into_body()).await.unwrap().to_vec()).unwrap()
+    }
+
+    #[actix_web::test]
+    async fn pages_are_recognised_by_the_scraper() {
+        let state = web::Data::new(MockState::new(offices()));
+
+        for (uri, expected) in [
+            (INDEX_PATH, PageState::Landing),
+            ("/services", PageState::ServiceSelection),
+            ("/offices", PageState::OfficeList),
+            ("/calendar/1", PageState::Calendar),
+            (
+                "/calendar/0",
+                PageState::Error(NO_APPOINTMENTS_MESSAGE.to_string()),
+            ),
+            ("/contact?office=1&date=2030-01-04", PageState::ContactForm),
+        ] {
+            assert_eq!(
+                PageState::detect(&get(&state, uri).await, &SelectorProfile::default()),
+                expected,
+                "{}",
+                uri
+            );
+        }
+    }
+
+    #[actix_web::test]
+    async fn offices_and_dates_parse_as_scripted() {
+        let state = web::Data::new(MockState::new(offices()));
+
+        let parsed =
+            parse_offices(&get(&state, "/offices").await, &SelectorProfile::default()).unwrap();
+        let summary: Vec<_> = parsed
+            .iter()
+            .map(|o| {
+                (
+                    o.name.as_str(),
+                    o.zip_code.as_str(),
+                    o.distance,
+                    o.is_reservable,
+                )
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("Garner", "27529", 8, false),
+                ("Raleigh West", "27610", 3, true),
+                ("Cary", "27511", 13, true),
+            ]
+        );
+
+        assert_eq!(
+            parse_calendar(
+                &get(&state, "/calendar/1").await,
+                &SelectorProfile::default()
+            )
+            .unwrap(),
+            vec![date("2030-01-04"), date("2030-01-29")]
+        );
+    }
+
+    #[actix_web::test]
+    async fn bookings_are_confirmed_or_refused() {
+        let state = web::Data::new(MockState::new(offices()));
+
+        let confirmed = post_booking(&state, "Raleigh West").await;
+        assert_eq!(
+            PageState::detect(&confirmed, &SelectorProfile::default()),
+            PageState::Confirmation
+        );
+        assert_eq!(state.bookings()[0].captcha_token, "token");
+
+        let refused = post_booking(&state, "Cary").await;
+        assert_eq!(
+            PageState::detect(&refused, &SelectorProfile::default()),
+            PageState::Error(UNABLE_MESSAGE.to_string())
+        );
+        assert_eq!(state.bookings().len(), 1);
+    }
+}
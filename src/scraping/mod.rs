@@ -1,2 +1,17 @@
+pub mod artifacts;
+pub mod canary;
 pub mod constants;
+pub mod discovery;
+pub mod error;
+#[cfg(test)]
+pub mod fake_portal;
+#[cfg(test)]
+pub mod fixtures;
+#[cfg(test)]
+pub mod mock_portal;
+pub mod page;
+pub mod parser;
+pub mod portal;
 pub mod scraper;
+pub mod selectors;
+pub mod wait;
@@ -0,0 +1,187 @@
+use once_cell::sync::Lazy;
+use scraper::{Html, Selector};
+use serde::{Deserialize, Serialize};
+
+use crate::scraping::constants::*;
+use crate::scraping::parser::selector;
+use crate::scraping::selectors::{SelectorProfile, class_css, id_css};
+
+/// Pages of the portal's booking wizard, in the order a booking visits them.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case", tag = "page", content = "message")]
+pub enum PageState {
+    /// The start page with the "Make an Appointment" button.
+    Landing,
+    ServiceSelection,
+    OfficeList,
+    /// An office's datepicker.
+    Calendar,
+    /// Time slots for the chosen date.
+    TimeSelection,
+    ContactForm,
+    Captcha,
+    Confirmation,
+    /// The portal showed an outage message or refused the chosen office or slot.
+    Error(String),
+    /// None of the known markers; usually a page that is still loading.
+    Unknown,
+}
+
+/// How to get from an unexpected page back to the office list.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RecoveryAction {
+    /// Keep navigating forward.
+    Continue,
+    /// Click the wizard's back button.
+    Back,
+    /// Load the landing page and navigate from scratch.
+    Restart,
+    /// The portal is down; wait before trying again.
+    BackOff,
+}
+
+static BODY: Lazy<Selector> = Lazy::new(|| Selector::parse("body").unwrap());
+
+impl PageState {
+    /// Identifies the page from its HTML. Messages take precedence over markup so
+    /// an error shown on top of a calendar is reported as an error.
+    pub fn detect(html: &str, selectors: &SelectorProfile) -> PageState {
+        let document = Html::parse_document(html);
+        let text: String = document
+            .select(&BODY)
+            .flat_map(|body| body.text())
+            .collect();
+        // A selector that doesn't parse matches nothing.
+        let has = |css: String| {
+            selector(&css).is_ok_and(|selector| document.select(&selector).next().is_some())
+        };
+
+        if let Some(message) = PORTAL_OUTAGE_MESSAGES
+            .iter()
+            .chain(OFFICE_UNAVAILABLE_MESSAGES)
+            .find(|message| text.contains(*message))
+        {
+            PageState::Error(message.to_string())
+        } else if CONFIRMATION_MESSAGES
+            .iter()
+            .any(|message| text.contains(message))
+        {
+            PageState::Confirmation
+        } else if has(id_css(&selectors.first_name_input_id)) {
+            PageState::ContactForm
+        } else if has(format!(
+            "{}, .g-recaptcha",
+            id_css(&selectors.recaptcha_response_id)
+        )) {
+            PageState::Captcha
+        } else if has(class_css(&selectors.time_slot_class)) {
+            PageState::TimeSelection
+        } else if has(class_css(&selectors.datepicker_class)) {
+            PageState::Calendar
+        } else if has(class_css(&selectors.office_item_class)) {
+            PageState::OfficeList
+        } else if has(format!("div{}", class_css(&selectors.item_child_class))) {
+            PageState::ServiceSelection
+        } else if has(id_css(&selectors.make_appointment_button_id)) {
+            PageState::Landing
+        } else {
+            PageState::Unknown
+        }
+    }
+
+    /// Whether the wizard can move from `self` to `next` in one step. Any page can
+    /// fail or restart; the back button returns to the office list.
+    pub fn can_transition_to(&self, next: &PageState) -> bool {
+        use PageState::*;
+
+        if matches!(next, Error(_) | Unknown | Landing) {
+            return true;
+        }
+        match self {
+            Landing => matches!(next, ServiceSelection),
+            ServiceSelection => matches!(next, OfficeList),
+            OfficeList => matches!(next, OfficeList | Calendar),
+            Calendar => matches!(next, Calendar | TimeSelection | ContactForm | OfficeList),
+            TimeSelection => matches!(next, TimeSelection | ContactForm | OfficeList),
+            ContactForm => matches!(next, Captcha | Confirmation | OfficeList),
+            Captcha => matches!(next, ContactForm | Confirmation),
+            Confirmation => false,
+            Error(_) | Unknown => true,
+        }
+    }
+
+    pub fn recovery(&self) -> RecoveryAction {
+        match self {
+            PageState::Landing | PageState::ServiceSelection | PageState::OfficeList => {
+                RecoveryAction::Continue
+            }
+            PageState::Calendar
+            | PageState::TimeSelection
+            | PageState::ContactForm
+            | PageState::Captcha => RecoveryAction::Back,
+            PageState::Error(message) if PORTAL_OUTAGE_MESSAGES.contains(&message.as_str()) => {
+                RecoveryAction::BackOff
+            }
+            PageState::Error(_) => RecoveryAction::Back,
+            PageState::Confirmation | PageState::Unknown => RecoveryAction::Restart,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scraping::fixtures::fixture;
+
+    #[test]
+    fn detects_each_wizard_page() {
+        for (html, page) in [
+            (fixture!("landing.html"), PageState::Landing),
+            (
+                fixture!("service_selection.html"),
+                PageState::ServiceSelection,
+            ),
+            (fixture!("office_list.html"), PageState::OfficeList),
+            (fixture!("calendar_2026_11.html"), PageState::Calendar),
+            (fixture!("calendar_2026_12.html"), PageState::Calendar),
+            (fixture!("calendar_2027_01.html"), PageState::Calendar),
+            (fixture!("time_selection.html"), PageState::TimeSelection),
+            (fixture!("contact_form.html"), PageState::ContactForm),
+            (fixture!("confirmation.html"), PageState::Confirmation),
+            ("<html><body></body></html>", PageState::Unknown),
+        ] {
+            assert_eq!(PageState::detect(html, &SelectorProfile::default()), page);
+        }
+    }
+
+    #[test]
+    fn detects_office_unavailable_messages() {
+        for (html, message) in [
+            (fixture!("no_appointments.html"), NO_APPOINTMENTS_MESSAGE),
+            (fixture!("select_date.html"), SELECT_DATE_MESSAGE),
+            (fixture!("unable.html"), UNABLE_MESSAGE),
+        ] {
+            let page = PageState::detect(html, &SelectorProfile::default());
+            assert_eq!(page, PageState::Error(message.to_string()));
+            assert_eq!(page.recovery(), RecoveryAction::Back);
+        }
+    }
+
+    #[test]
+    fn outage_messages_back_off() {
+        let page = PageState::detect(
+            "<html><body><h1>Service Unavailable</h1></body></html>",
+            &SelectorProfile::default(),
+        );
+        assert_eq!(page.recovery(), RecoveryAction::BackOff);
+    }
+
+    #[test]
+    fn wizard_transitions() {
+        assert!(PageState::OfficeList.can_transition_to(&PageState::Calendar));
+        assert!(PageState::Calendar.can_transition_to(&PageState::OfficeList));
+        assert!(PageState::ContactForm.can_transition_to(&PageState::Confirmation));
+        assert!(!PageState::Landing.can_transition_to(&PageState::Calendar));
+        assert!(!PageState::Confirmation.can_transition_to(&PageState::OfficeList));
+    }
+}
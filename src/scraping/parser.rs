@@ -0,0 +1,309 @@
+use chrono::{Datelike, Local, NaiveDate};
+use once_cell::sync::Lazy;
+use regex::Regex;
+use scraper::{ElementRef, Html, Selector};
+
+use crate::scraping::error::{ScraperError, ScraperResult};
+use crate::scraping::portal::PortalOffice;
+use crate::scraping::selectors::{SelectorProfile, class_css};
+
+static ZIP_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r"\b\d{5}\b").unwrap());
+
+/// Parses a CSS selector built from the selector profile.
+pub(crate) fn selector(css: &str) -> ScraperResult<Selector> {
+    Selector::parse(css)
+        .map_err(|e| ScraperError::Config(format!("invalid selector '{}': {}", css, e)))
+}
+
+// Parts of jQuery UI's datepicker markup, which the portal doesn't control.
+static DIV: Lazy<Selector> = Lazy::new(|| Selector::parse("div").unwrap());
+static MONTH: Lazy<Selector> = Lazy::new(|| Selector::parse(".ui-datepicker-month").unwrap());
+static YEAR: Lazy<Selector> = Lazy::new(|| Selector::parse(".ui-datepicker-year").unwrap());
+
+/// Text of an element with whitespace collapsed, roughly what WebDriver reports.
+fn text_of(element: ElementRef) -> String {
+    element
+        .text()
+        .flat_map(str::split_whitespace)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Reads every office on an office list page.
+pub fn parse_offices(html: &str, selectors: &SelectorProfile) -> ScraperResult<Vec<PortalOffice>> {
+    let document = Html::parse_document(html);
+    let office = selector(&class_css(&selectors.office_item_class))?;
+    let address = selector(&class_css(&selectors.item_child_class))?;
+    document
+        .select(&office)
+        .map(|office_el| parse_office(office_el, &address, selectors))
+        .collect()
+}
+
+fn parse_office(
+    office_el: ElementRef,
+    address: &Selector,
+    selectors: &SelectorProfile,
+) -> ScraperResult<PortalOffice> {
+    let is_reservable = office_el
+        .value()
+        .classes()
+        .any(|class| class == selectors.active_unit_class);
+
+    // The second div holds the name and the last one the distance.
+    let office_divs: Vec<ElementRef> = office_el.select(&DIV).collect();
+    let name = office_divs
+        .get(1)
+        .map(|div| text_of(*div))
+        .unwrap_or_default();
+
+    let addr = office_el
+        .select(address)
+        .next()
+        .map(text_of)
+        .ok_or_else(|| ScraperError::DomDrift(format!("office '{}' has no address", name)))?;
+
+    let zip_code = ZIP_REGEX
+        .find(&addr)
+        .map(|m| m.as_str().to_string())
+        .unwrap_or_default();
+
+    let street_address = addr
+        .replace(&zip_code, "")
+        .trim()
+        .trim_end_matches(',')
+        .to_string();
+
+    let distance = match office_divs.last() {
+        Some(div) => {
+            let text = text_of(*div).replace(" Miles", "").replace("text=", "");
+            let parsed_distance = text
+                .parse::<f32>()
+                .map_err(|_| ScraperError::DomDrift(format!("unparseable distance '{}'", text)))?;
+            parsed_distance.round() as u16
+        }
+        None => 0,
+    };
+
+    Ok(PortalOffice {
+        name,
+        street_address,
+        zip_code,
+        distance,
+        is_reservable,
+    })
+}
+
+/// Reads the dates an office's datepicker marks as available. Days are dated by
+/// their cell's `data-month`/`data-year` when present and by the calendar header
+/// otherwise.
+pub fn parse_calendar(html: &str, selectors: &SelectorProfile) -> ScraperResult<Vec<NaiveDate>> {
+    let document = Html::parse_document(html);
+    let available_date = selector(&format!("a{}", class_css(&selectors.available_date_class)))?;
+
+    let header_month = document
+        .select(&MONTH)
+        .next()
+        .and_then(|el| month_number(&text_of(el)));
+    let header_year = document
+        .select(&YEAR)
+        .next()
+        .and_then(|el| text_of(el).parse::<i32>().ok())
+        .unwrap_or_else(|| Local::now().year());
+
+    let mut available_dates = Vec::new();
+    for date_el in document.select(&available_date) {
+        let Ok(day) = text_of(date_el).parse::<u32>() else {
+            continue;
+        };
+
+        let cell = date_el.parent().and_then(ElementRef::wrap);
+        let cell_attr = |name: &str| cell.and_then(|cell| cell.value().attr(name));
+        // data-month is zero-based, like JavaScript's Date.
+        let month = cell_attr("data-month")
+            .and_then(|m| m.parse::<u32>().ok())
+            .map(|m| m + 1)
+            .or(header_month)
+            .ok_or_else(|| ScraperError::DomDrift("calendar has no month".to_string()))?;
+        let year = cell_attr("data-year")
+            .and_then(|y| y.parse::<i32>().ok())
+            .unwrap_or(header_year);
+
+        if let Some(date) = NaiveDate::from_ymd_opt(year, month, day) {
+            available_dates.push(date);
+        }
+    }
+
+    Ok(available_dates)
+}
+
+/// Converts a month name to its number (1-12).
+fn month_number(name: &str) -> Option<u32> {
+    let month = match name {
+        "January" => 1,
+        "February" => 2,
+        "March" => 3,
+        "April" => 4,
+        "May" => 5,
+        "June" => 6,
+        "July" => 7,
+        "August" => 8,
+        "September" => 9,
+        "October" => 10,
+        "November" => 11,
+        "December" => 12,
+        _ => return None,
+    };
+    Some(month)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::dmvservice::DMVService;
+    use crate::models::offices::OfficeAvailability;
+    use crate::scraping::fixtures::{date, fixture};
+
+    fn availability(html: &str) -> Vec<OfficeAvailability> {
+        let service: DMVService = "first-time".parse().unwrap();
+        parse_offices(html, &SelectorProfile::default())
+            .unwrap()
+            .into_iter()
+            .map(|office| office.into_availability(&service, "fixture".to_string()))
+            .collect()
+    }
+
+    fn office(
+        name: &str,
+        street_address: &str,
+        zip_code: &str,
+        distance: u16,
+        is_reservable: bool,
+    ) -> OfficeAvailability {
+        OfficeAvailability {
+            is_reservable,
+            office_name: name.to_string(),
+            street_address: street_address.to_string(),
+            distance,
+            zip_code: zip_code.to_string(),
+            available_dates: Vec::new(),
+            selected_date: None,
+            service: "Driver License - First Time".to_string(),
+            source_listener: "fixture".to_string(),
+            scraped_at: Default::default(),
+        }
+    }
+
+    #[test]
+    fn office_list() {
+        let mut parsed = availability(fixture!("office_list.html"));
+        for office in &mut parsed {
+            office.scraped_at = Default::default();
+        }
+
+        assert_eq!(
+            parsed,
+            vec![
+                office(
+                    "Raleigh West",
+                    "4121 New Bern Ave, Raleigh, NC",
+                    "27610",
+                    3,
+                    true
+                ),
+                office("Garner", "1240 US-70, Garner, NC", "27529", 8, false),
+                office("Cary", "1387 SE Maynard Rd, Cary, NC", "27511", 13, true),
+                office(
+                    "Durham South",
+                    "3620 N Roxboro St, Durham, NC",
+                    "27704",
+                    24,
+                    false
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn office_without_address_is_drift() {
+        let html = r#"<div class="QflowObjectItem Active-Unit"><div><div>Nowhere</div></div><div>1 Miles</div></div>"#;
+        assert!(matches!(
+            parse_offices(html, &SelectorProfile::default()),
+            Err(ScraperError::DomDrift(_))
+        ));
+    }
+
+    #[test]
+    fn calendar_dated_by_header() {
+        assert_eq!(
+            parse_calendar(
+                fixture!("calendar_2026_11.html"),
+                &SelectorProfile::default()
+            )
+            .unwrap(),
+            vec![
+                date("2026-11-09"),
+                date("2026-11-12"),
+                date("2026-11-13"),
+                date("2026-11-30"),
+            ]
+        );
+    }
+
+    #[test]
+    fn calendar_dated_by_cells() {
+        assert_eq!(
+            parse_calendar(
+                fixture!("calendar_2026_12.html"),
+                &SelectorProfile::default()
+            )
+            .unwrap(),
+            vec![
+                date("2026-12-01"),
+                date("2026-12-02"),
+                date("2026-12-03"),
+                date("2026-12-22"),
+                date("2026-12-31"),
+            ]
+        );
+        assert_eq!(
+            parse_calendar(
+                fixture!("calendar_2027_01.html"),
+                &SelectorProfile::default()
+            )
+            .unwrap(),
+            vec![date("2027-01-04"), date("2027-01-05"), date("2027-01-29")]
+        );
+    }
+
+    #[test]
+    fn pages_without_a_calendar_have_no_dates() {
+        for html in [
+            fixture!("office_list.html"),
+            fixture!("no_appointments.html"),
+            fixture!("unable.html"),
+            fixture!("contact_form.html"),
+            fixture!("confirmation.html"),
+        ] {
+            assert_eq!(
+                parse_calendar(html, &SelectorProfile::default()).unwrap(),
+                Vec::new()
+            );
+        }
+    }
+
+    #[test]
+    fn pages_without_offices_have_no_offices() {
+        for html in [
+            fixture!("calendar_2026_12.html"),
+            fixture!("no_appointments.html"),
+            fixture!("contact_form.html"),
+            fixture!("confirmation.html"),
+        ] {
+            assert_eq!(
+                parse_offices(html, &SelectorProfile::default()).unwrap(),
+                Vec::new()
+            );
+        }
+    }
+}
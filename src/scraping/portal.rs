@@ -0,0 +1,509 @@
+use async_trait::async_trait;
+use chrono::{Datelike, NaiveDate, Utc};
+use serde_json::Value;
+use std::sync::Arc;
+use std::time::Duration;
+use thirtyfour::prelude::*;
+use tracing::info;
+
+use crate::config::AppConfig;
+use crate::models::dmvservice::DMVService;
+use crate::models::offices::OfficeAvailability;
+use crate::scraping::artifacts;
+use crate::scraping::error::{ScraperError, ScraperResult};
+use crate::scraping::page::{PageState, RecoveryAction};
+use crate::scraping::parser::{parse_calendar, parse_offices};
+use crate::scraping::selectors::{SelectorProfile, class_css};
+use crate::scraping::wait::{PAGE_TIMEOUT, POLL_INTERVAL, wait_for};
+
+/// An office as listed on the portal's office list.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PortalOffice {
+    pub name: String,
+    pub street_address: String,
+    pub zip_code: String,
+    /// Miles from the spoofed location, rounded.
+    pub distance: u16,
+    /// Whether the portal shows the office as bookable.
+    pub is_reservable: bool,
+}
+
+impl PortalOffice {
+    /// The office as reported to clients, before its calendar has been read.
+    pub fn into_availability(
+        self,
+        service: &DMVService,
+        source_listener: String,
+    ) -> OfficeAvailability {
+        OfficeAvailability {
+            is_reservable: self.is_reservable,
+            office_name: self.name,
+            street_address: self.street_address,
+            zip_code: self.zip_code,
+            distance: self.distance,
+            available_dates: Vec::new(),
+            selected_date: None,
+            service: service.title().to_string(),
+            source_listener,
+            scraped_at: Utc::now(),
+        }
+    }
+}
+
+/// What the contact form is filled with.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ContactDetails {
+    pub first_name: String,
+    pub last_name: String,
+    pub phone_number: String,
+    /// The proxy address, not the requester's real one.
+    pub email: String,
+}
+
+/// The steps of the portal's booking wizard. Methods that leave a page return the
+/// page the browser landed on so callers can react to errors the portal shows.
+#[async_trait]
+pub trait AppointmentPortal: Send + Sync {
+    /// Navigates to the office list for `service` from wherever the browser is and
+    /// reads it.
+    async fn list_offices(&self, service: &DMVService) -> ScraperResult<Vec<PortalOffice>>;
+    /// Clicks an office on the office list.
+    async fn open_office(&self, office_name: &str) -> ScraperResult<PageState>;
+    /// Dates the open office's calendar marks as available.
+    async fn available_dates(&self) -> ScraperResult<Vec<NaiveDate>>;
+    /// Picks `date` on the calendar and moves on to its time slots or the contact form.
+    async fn select_date(&self, date: NaiveDate) -> ScraperResult<PageState>;
+    /// Picks the first offered time slot and moves on.
+    async fn select_time(&self) -> ScraperResult<PageState>;
+    /// Leaves `from` for the office list using the wizard's back button.
+    async fn back_to_office_list(&self, from: &PageState) -> ScraperResult<()>;
+    async fn fill_contact_form(&self, contact: &ContactDetails) -> ScraperResult<()>;
+    /// Hands a solved reCAPTCHA token to the page and submits the contact form.
+    async fn submit_captcha_token(&self, token: &str) -> ScraperResult<()>;
+    /// Waits for the portal to confirm or refuse the submitted booking.
+    async fn read_confirmation(&self) -> ScraperResult<PageState>;
+    /// Saves what the browser shows for later inspection, because of `reason`.
+    /// Does nothing unless the portal keeps failure artifacts.
+    async fn capture(&self, _reason: &str) {}
+}
+
+/// Outside services a booking needs besides the portal.
+#[async_trait]
+pub trait BookingServices: Send + Sync {
+    /// Contact details for a booking on one of `dates`.
+    async fn contact_details(&self, dates: &[String]) -> ScraperResult<ContactDetails>;
+    /// A solved reCAPTCHA token for the portal.
+    async fn captcha_token(&self) -> ScraperResult<String>;
+}
+
+// --------------------------------------------------------------------------
+// Page navigation
+// --------------------------------------------------------------------------
+
+/// Upper bound on wizard steps between any page and the office list.
+const MAX_NAVIGATION_STEPS: usize = 8;
+
+/// How long time slots get to render after a date is picked before the date is
+/// assumed to have none.
+const TIME_SLOT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Detects the page the browser is on.
+pub async fn current_page(
+    driver: &WebDriver,
+    selectors: &SelectorProfile,
+) -> ScraperResult<PageState> {
+    Ok(PageState::detect(&driver.source().await?, selectors))
+}
+
+/// Waits until the browser shows a recognisable page.
+pub(crate) async fn settled_page(
+    driver: &WebDriver,
+    selectors: &SelectorProfile,
+) -> ScraperResult<PageState> {
+    wait_for(
+        "a recognisable page",
+        PAGE_TIMEOUT,
+        POLL_INTERVAL,
+        || async move {
+            let page = current_page(driver, selectors).await?;
+            Ok((page != PageState::Unknown).then_some(page))
+        },
+    )
+    .await
+}
+
+/// Waits until the browser leaves `from` for another recognisable page and checks
+/// that the wizard can actually move there.
+pub(crate) async fn next_page(
+    driver: &WebDriver,
+    selectors: &SelectorProfile,
+    from: &PageState,
+) -> ScraperResult<PageState> {
+    let to = wait_for(
+        &format!("the page after {:?}", from),
+        PAGE_TIMEOUT,
+        POLL_INTERVAL,
+        || async move {
+            let page = current_page(driver, selectors).await?;
+            Ok((page != *from && page != PageState::Unknown).then_some(page))
+        },
+    )
+    .await?;
+
+    if from.can_transition_to(&to) {
+        Ok(to)
+    } else {
+        Err(ScraperError::DomDrift(format!(
+            "unexpected {:?} page after {:?}",
+            to, from
+        )))
+    }
+}
+
+/// Steps through the wizard until the office list for `service` is shown,
+/// recovering from whatever page the browser is on.
+async fn navigate_to_office_list(
+    driver: &WebDriver,
+    selectors: &SelectorProfile,
+    service: &DMVService,
+) -> ScraperResult<()> {
+    let mut page = settled_page(driver, selectors).await?;
+
+    for _ in 0..MAX_NAVIGATION_STEPS {
+        match &page {
+            PageState::OfficeList => return Ok(()),
+            PageState::Landing => {
+                driver
+                    .find(By::Id(&selectors.make_appointment_button_id))
+                    .await?
+                    .click()
+                    .await?
+            }
+            PageState::ServiceSelection => click_service_tile(driver, selectors, service).await?,
+            other => match other.recovery() {
+                RecoveryAction::BackOff => {
+                    return Err(ScraperError::PortalOutage(format!("{:?}", other)));
+                }
+                RecoveryAction::Back
+                    if driver.find(By::Id(&selectors.back_button_id)).await.is_ok() =>
+                {
+                    driver
+                        .find(By::Id(&selectors.back_button_id))
+                        .await?
+                        .click()
+                        .await?
+                }
+                _ => driver.goto(&selectors.base_url).await?,
+            },
+        }
+        page = next_page(driver, selectors, &page).await?;
+    }
+
+    Err(ScraperError::DomDrift(format!(
+        "office list not reached, stuck on {:?}",
+        page
+    )))
+}
+
+pub(crate) async fn click_service_tile(
+    driver: &WebDriver,
+    selectors: &SelectorProfile,
+    service: &DMVService,
+) -> ScraperResult<()> {
+    let selector = service.selector();
+    let tile_css = &format!("div{}", class_css(&selectors.item_child_class));
+    wait_for(
+        &format!("the '{}' service tile", service.title()),
+        PAGE_TIMEOUT,
+        POLL_INTERVAL,
+        || async move {
+            let elements = driver.find_all(By::Css(tile_css)).await?;
+            for elem in elements {
+                if elem.text().await?.contains(selector) && elem.is_clickable().await? {
+                    return Ok(Some(elem));
+                }
+            }
+            Ok(None)
+        },
+    )
+    .await?
+    .click()
+    .await?;
+    Ok(())
+}
+
+/// Clicks the wizard's next button and waits until it is replaced by the next step.
+async fn click_next(driver: &WebDriver, selectors: &SelectorProfile) -> ScraperResult<()> {
+    let next_button = driver
+        .find(By::Css(class_css(&selectors.next_button_class)))
+        .await?;
+    next_button.click().await?;
+    wait_for("the next step", PAGE_TIMEOUT, POLL_INTERVAL, || async {
+        Ok((!next_button.is_present().await?).then_some(()))
+    })
+    .await
+}
+
+/// Types `value` into the input with `id` once it is visible.
+async fn fill_field(driver: &WebDriver, id: &str, value: &str) -> ScraperResult<()> {
+    let field = wait_for(id, PAGE_TIMEOUT, POLL_INTERVAL, || async move {
+        let field = driver.find(By::Id(id)).await?;
+        Ok(field.is_displayed().await?.then_some(field))
+    })
+    .await?;
+    field.click().await?;
+    field.send_keys(value).await?;
+    Ok(())
+}
+
+// --------------------------------------------------------------------------
+// WebDriver portal
+// --------------------------------------------------------------------------
+
+/// The real portal, driven through a Chrome session.
+pub struct WebDriverPortal<'a> {
+    driver: &'a WebDriver,
+    selectors: Arc<SelectorProfile>,
+    /// Settings and request ID failure artifacts are saved with.
+    artifacts: Option<(&'a AppConfig, String)>,
+}
+
+impl<'a> WebDriverPortal<'a> {
+    pub fn new(driver: &'a WebDriver, selectors: Arc<SelectorProfile>) -> Self {
+        WebDriverPortal {
+            driver,
+            selectors,
+            artifacts: None,
+        }
+    }
+
+    /// Saves failure artifacts for `request_id` on `capture`.
+    pub fn capturing_failures(mut self, config: &'a AppConfig, request_id: String) -> Self {
+        self.artifacts = Some((config, request_id));
+        self
+    }
+
+    async fn current_page(&self) -> ScraperResult<PageState> {
+        current_page(self.driver, &self.selectors).await
+    }
+
+    async fn next_page(&self, from: &PageState) -> ScraperResult<PageState> {
+        next_page(self.driver, &self.selectors, from).await
+    }
+
+    async fn click_next(&self) -> ScraperResult<()> {
+        click_next(self.driver, &self.selectors).await
+    }
+}
+
+#[async_trait]
+impl AppointmentPortal for WebDriverPortal<'_> {
+    async fn list_offices(&self, service: &DMVService) -> ScraperResult<Vec<PortalOffice>> {
+        navigate_to_office_list(self.driver, &self.selectors, service).await?;
+        parse_offices(&self.driver.source().await?, &self.selectors)
+    }
+
+    async fn open_office(&self, office_name: &str) -> ScraperResult<PageState> {
+        let offices = parse_offices(&self.driver.source().await?, &self.selectors)?;
+        let index = offices
+            .iter()
+            .position(|office| office.name == office_name)
+            .ok_or_else(|| {
+                ScraperError::DomDrift(format!(
+                    "office '{}' is not on the office list",
+                    office_name
+                ))
+            })?;
+
+        let office_elements = self
+            .driver
+            .find_all(By::Css(class_css(&self.selectors.office_item_class)))
+            .await?;
+        let office_el = office_elements.get(index).ok_or_else(|| {
+            ScraperError::DomDrift(format!("office '{}' disappeared", office_name))
+        })?;
+        office_el.click().await?;
+        self.next_page(&PageState::OfficeList).await
+    }
+
+    async fn available_dates(&self) -> ScraperResult<Vec<NaiveDate>> {
+        parse_calendar(&self.driver.source().await?, &self.selectors)
+    }
+
+    async fn select_date(&self, date: NaiveDate) -> ScraperResult<PageState> {
+        // Only available days are links worth clicking; other anchors on the page
+        // can carry the same number.
+        let day = date.day().to_string();
+        let mut day_link = None;
+        for link in self
+            .driver
+            .find_all(By::Css(format!(
+                "a{}",
+                class_css(&self.selectors.available_date_class)
+            )))
+            .await?
+        {
+            if link.text().await?.trim() == day {
+                day_link = Some(link);
+                break;
+            }
+        }
+        day_link
+            .ok_or_else(|| ScraperError::DomDrift(format!("{} is not an available date", date)))?
+            .click()
+            .await?;
+
+        // Times may render next to the calendar once a date is picked; wait for
+        // them, or for the portal to move on by itself, before clicking Next.
+        let settled = wait_for(
+            "the time slots",
+            TIME_SLOT_TIMEOUT,
+            POLL_INTERVAL,
+            || async move {
+                let page = self.current_page().await?;
+                Ok((page != PageState::Calendar).then_some(page))
+            },
+        )
+        .await;
+        match settled {
+            Ok(page) => return Ok(page),
+            Err(ScraperError::NavigationTimeout(_)) => {}
+            Err(e) => return Err(e),
+        }
+
+        let next_css = &class_css(&self.selectors.next_button_class);
+        wait_for(
+            "the next button",
+            PAGE_TIMEOUT,
+            POLL_INTERVAL,
+            || async move {
+                let button = self.driver.find(By::Css(next_css)).await?;
+                Ok(button.is_clickable().await?.then_some(()))
+            },
+        )
+        .await?;
+        self.click_next().await?;
+        self.next_page(&PageState::Calendar).await
+    }
+
+    async fn select_time(&self) -> ScraperResult<PageState> {
+        self.driver
+            .find(By::Css(class_css(&self.selectors.time_slot_class)))
+            .await?
+            .click()
+            .await?;
+        self.click_next().await?;
+        self.next_page(&PageState::TimeSelection).await
+    }
+
+    async fn back_to_office_list(&self, from: &PageState) -> ScraperResult<()> {
+        self.driver
+            .find(By::Id(&self.selectors.back_button_id))
+            .await?
+            .click()
+            .await?;
+        match self.next_page(from).await? {
+            PageState::OfficeList => Ok(()),
+            other => Err(ScraperError::DomDrift(format!(
+                "back button led to {:?} instead of the office list",
+                other
+            ))),
+        }
+    }
+
+    async fn fill_contact_form(&self, contact: &ContactDetails) -> ScraperResult<()> {
+        let selectors = &self.selectors;
+        fill_field(
+            self.driver,
+            &selectors.first_name_input_id,
+            &contact.first_name,
+        )
+        .await?;
+        fill_field(
+            self.driver,
+            &selectors.last_name_input_id,
+            &contact.last_name,
+        )
+        .await?;
+        fill_field(
+            self.driver,
+            &selectors.phone_number_input_id,
+            &contact.phone_number,
+        )
+        .await?;
+        fill_field(self.driver, &selectors.email_input_id, &contact.email).await?;
+        fill_field(
+            self.driver,
+            &selectors.confirm_email_input_id,
+            &contact.email,
+        )
+        .await?;
+        Ok(())
+    }
+
+    async fn submit_captcha_token(&self, token: &str) -> ScraperResult<()> {
+        let js = r#"
+            const response = document.getElementById(arguments[1]);
+            response.innerHTML = arguments[0];
+            response.style.display = 'block';
+        "#;
+
+        info!("executing js for captcha");
+
+        let args: Vec<Value> = vec![
+            Value::String(token.to_string()),
+            Value::String(self.selectors.recaptcha_response_id.clone()),
+        ];
+        self.driver.execute(js, Arc::from(args)).await?;
+
+        let js_callback = r#"
+            CaptchaCallBack(arguments[0]);
+        "#;
+
+        self.driver
+            .execute(
+                js_callback,
+                Arc::from(vec![Value::String(token.to_string())]),
+            )
+            .await?;
+
+        // The form takes up to two wizard steps to submit.
+        for _ in 0..2 {
+            if self
+                .driver
+                .find(By::Css(class_css(&self.selectors.next_button_class)))
+                .await
+                .is_err()
+            {
+                break;
+            }
+            self.click_next().await?;
+            if matches!(
+                self.current_page().await?,
+                PageState::Confirmation | PageState::Error(_)
+            ) {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    async fn read_confirmation(&self) -> ScraperResult<PageState> {
+        wait_for(
+            "the confirmation",
+            PAGE_TIMEOUT,
+            POLL_INTERVAL,
+            || async move {
+                let page = self.current_page().await?;
+                Ok(matches!(page, PageState::Confirmation | PageState::Error(_)).then_some(page))
+            },
+        )
+        .await
+    }
+
+    async fn capture(&self, reason: &str) {
+        if let Some((config, request_id)) = &self.artifacts {
+            artifacts::capture_failure(self.driver, config, request_id, reason).await;
+        }
+    }
+}
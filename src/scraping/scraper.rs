@@ -1,33 +1,38 @@
+use crate::config::AppConfig;
 use crate::models::dmvservice::DMVService;
 use crate::models::email::RegisterRequest;
 use crate::models::offices::OfficeAvailability;
 use crate::models::zipcode;
-use crate::scraping::constants::*;
+use crate::registry::{ListenerPhase, ListenerTracker};
+use crate::scraping::artifacts;
+use crate::scraping::constants::NO_APPOINTMENTS_MESSAGE;
+use crate::scraping::error::{Recovery, ScraperError, ScraperResult};
+use crate::scraping::page::{PageState, RecoveryAction};
+use crate::scraping::portal::{
+    AppointmentPortal, BookingServices, ContactDetails, WebDriverPortal,
+};
+use crate::scraping::selectors::{self, SelectorProfile};
 use anyhow::Result;
+use async_trait::async_trait;
 use captcha_oxide::CaptchaSolver;
 use captcha_oxide::CaptchaTask;
 use captcha_oxide::captcha_types::recaptcha::RecaptchaV2;
-use chrono::{Datelike, Local, NaiveDate};
+use chrono::{Local, NaiveDate};
 use country::Country;
-use dotenv::dotenv;
 use once_cell::sync::Lazy;
 use postal_code::PostalCode;
-use regex::Regex;
 use reqwest::Client;
-use serde_json::Value;
 use serde_json::json;
-use std::error::Error;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use std::sync::Mutex;
 use std::time::Duration;
-use tempfile::tempdir;
 use thirtyfour::extensions::cdp::ChromeCommand;
 use thirtyfour::prelude::*;
-use thirtyfour::support::sleep;
 use tokio::sync::mpsc;
 use tokio::time::interval;
-use tracing::{error, info};
-use uuid::Uuid;
+use tracing::{error, info, warn};
 /**
 NC DMV has a bug where when an appointment is in the proccess of
 being booked it shows as blue (possible to be booked) when really it is taken,
@@ -35,19 +40,152 @@ the client has just not finished the form and submitted
 
 this tracks those so we dont miss anything
 */
-
 static FALSLEY_ENABLED_LOCATIONS: Lazy<Mutex<Vec<String>>> = Lazy::new(|| Mutex::new(vec![]));
 
+/// Prefix of the temporary Chrome profile directory each session uses.
+pub const CHROME_PROFILE_PREFIX: &str = "chrome-user-data-";
+
+/// Chrome profile directories this process created and has not removed yet.
+/// The temp dir is shared, so shutdown only cleans up what is listed here.
+static CHROME_PROFILES: Lazy<Mutex<HashSet<PathBuf>>> = Lazy::new(|| Mutex::new(HashSet::new()));
+
+/// Consecutive failed sweeps after which the Chrome session is restarted.
+const MAX_CONSECUTIVE_ERRORS: u32 = 5;
+
+/// Consecutive failed sessions after which the listener gives up.
+const MAX_SESSION_RESTARTS: u32 = 5;
+
+/// Exponential backoff starting at 30 seconds, capped at 10 minutes.
+fn backoff_delay(attempt: u32) -> Duration {
+    Duration::from_secs(30 * 2u64.pow(attempt.saturating_sub(1).min(5)))
+        .min(Duration::from_secs(600))
+}
+
+/// A throwaway Chrome profile directory, removed when dropped.
+pub struct ChromeProfile {
+    path: PathBuf,
+}
+
+impl ChromeProfile {
+    fn create() -> ScraperResult<Self> {
+        let path = tempfile::Builder::new()
+            .prefix(CHROME_PROFILE_PREFIX)
+            .tempdir()
+            .map_err(|e| ScraperError::Config(format!("cannot create Chrome profile: {}", e)))?
+            .keep();
+        CHROME_PROFILES.lock().unwrap().insert(path.clone());
+        Ok(ChromeProfile { path })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for ChromeProfile {
+    fn drop(&mut self) {
+        // Chrome may still hold files open; the directory then stays listed so
+        // shutdown can try again.
+        match std::fs::remove_dir_all(&self.path) {
+            Ok(()) => {
+                CHROME_PROFILES.lock().unwrap().remove(&self.path);
+            }
+            Err(e) => warn!("Failed to remove {}: {:?}", self.path.display(), e),
+        }
+    }
+}
+
+/// Removes the Chrome profiles this process created that are still on disk.
+pub fn remove_chrome_profiles() {
+    let profiles: Vec<PathBuf> = CHROME_PROFILES.lock().unwrap().drain().collect();
+    for path in profiles {
+        if let Err(e) = std::fs::remove_dir_all(&path)
+            && e.kind() != std::io::ErrorKind::NotFound
+        {
+            warn!("Failed to remove {}: {:?}", path.display(), e);
+        }
+    }
+}
+
+/// Starts a headless Chrome session with a throwaway profile directory, which is
+/// removed when the returned guard is dropped. Callers must quit the driver.
+pub async fn start_chrome(config: &AppConfig) -> ScraperResult<(WebDriver, ChromeProfile)> {
+    let mut caps = DesiredCapabilities::chrome();
+    let profile_dir = ChromeProfile::create()?;
+
+    //bc we run in a vm these help for optimization
+    caps.add_arg("--headless")?;
+    caps.add_arg("--no-first-run")?;
+    caps.add_arg("--disable-popup-blocking")?;
+    caps.add_arg("--disable-default-apps")?;
+    caps.add_arg("--disable-sync")?;
+    caps.add_arg("--remote-debugging-port=0")?;
+    caps.add_arg("--disable-gpu")?;
+    caps.add_arg("--no-sandbox")?;
+    caps.add_arg("--disable-dev-shm-usage")?;
+    caps.add_arg("--use-fake-ui-for-media-stream")?;
+    caps.add_arg("--use-fake-device-for-media-stream")?;
+
+    caps.add_arg(format!("--user-data-dir={}", profile_dir.path().display()).as_str())?;
+
+    let driver = WebDriver::new(&config.webdriver_url, caps).await?;
+    Ok((driver, profile_dir))
+}
+
+/// Grants the portal geolocation and reports `latitude`/`longitude` to it, which
+/// the office list sorts by.
+pub(crate) async fn spoof_location(
+    driver: &WebDriver,
+    selectors: &SelectorProfile,
+    latitude: f64,
+    longitude: f64,
+) -> ScraperResult<()> {
+    let grant_command = ChromeCommand::ExecuteCdpCommand(
+        "Browser.grantPermissions".to_string(),
+        json!({
+            "permissions": ["geolocation"],
+            "origin": selectors.origin()
+        }),
+    );
+
+    driver.cmd(grant_command).await?;
+
+    // Send Chrome DevTools Protocol command to override geolocation
+    let spoof_location_command = ChromeCommand::ExecuteCdpCommand(
+        "Page.setGeolocationOverride".to_string(),
+        json!({
+            "latitude": latitude,
+            "longitude": longitude,
+            "accuracy": 100.0
+        }),
+    );
+
+    driver.cmd(spoof_location_command).await?;
+    Ok(())
+}
+
+/// What happened when the scraper tried to book a reservable office.
+enum OfficeOutcome {
+    /// The office had no matching slot or refused it; the browser is back on the office list.
+    Unavailable,
+    /// Dry run: the office offers a requested date; the browser is back on the office list.
+    Matched,
+    Booked,
+}
+
 pub struct NCDMVScraper {
+    config: Arc<AppConfig>,
     name: String,
     phone_number: String,
     email: String,
     zipcode: String,
     max_distance: u16,
+    dry_run: bool,
 }
 
 impl NCDMVScraper {
     pub async fn new(
+        config: Arc<AppConfig>,
         zipcode: String,
         max_distance: u16,
         name: String,
@@ -56,18 +194,31 @@ impl NCDMVScraper {
     ) -> Result<Self> {
         if Self::validate(&zipcode).await? {
             Ok(NCDMVScraper {
+                config,
                 name,
                 phone_number,
                 email,
                 zipcode,
                 max_distance,
+                dry_run: false,
             })
         } else {
             Err(anyhow::anyhow!("Invalid ZIP code"))
         }
     }
 
-    async fn validate(zip_code: &String) -> Result<bool> {
+    /// Reads calendars and reports matching dates without booking. The global
+    /// `dry_run` setting applies on top of this.
+    pub fn with_dry_run(mut self, dry_run: bool) -> Self {
+        self.dry_run = dry_run;
+        self
+    }
+
+    fn is_dry_run(&self) -> bool {
+        self.dry_run || self.config.dry_run
+    }
+
+    async fn validate(zip_code: &str) -> Result<bool> {
         match PostalCode::new(Country::USA, zip_code) {
             Ok(code) => Ok(code.country() == &Country::USA),
             Err(_) => Ok(false),
@@ -79,541 +230,141 @@ impl NCDMVScraper {
         zip_code: String,
         refresh_interval_secs: u64,
         tx: mpsc::Sender<Vec<OfficeAvailability>>,
-        selector: String,
+        service: DMVService,
         dates: Vec<String>,
-    ) -> WebDriverResult<()> {
-        let mut caps = DesiredCapabilities::chrome();
-        let tmp_dir = tempdir()?;
-
-        //bc we run in a vm these help for optimization
-        caps.add_arg("--headless")?;
-        caps.add_arg("--no-first-run")?;
-        caps.add_arg("--disable-popup-blocking")?;
-        caps.add_arg("--disable-default-apps")?;
-        caps.add_arg("--disable-sync")?;
-        caps.add_arg("--remote-debugging-port=0")?;
-        caps.add_arg("--disable-gpu")?;
-        caps.add_arg("--no-sandbox")?;
-        caps.add_arg("--disable-dev-shm-usage")?;
-        caps.add_arg("--use-fake-ui-for-media-stream")?;
-        caps.add_arg("--use-fake-device-for-media-stream")?;
-
-        caps.add_arg(format!("--user-data-dir={}", tmp_dir.path().display()).as_str())?;
+        tracker: ListenerTracker,
+    ) -> ScraperResult<()> {
+        // Removed when dropped, including when the listener task is aborted.
+        let (driver, _profile_dir) = start_chrome(&self.config).await?;
+        tracker.set_phase(ListenerPhase::Navigating);
+
+        let result = self
+            .run_session(
+                &driver,
+                zip_code,
+                refresh_interval_secs,
+                tx,
+                service,
+                dates,
+                &tracker,
+            )
+            .await;
 
-        let user_data_dir = format!("/tmp/chrome-user-data-{}", Uuid::new_v4());
-        caps.add_arg(&format!("--user-data-dir={}", user_data_dir))?;
+        info!("Quitting Chrome session");
+        if let Err(e) = driver.quit().await {
+            error!("Failed to quit Chrome session: {:?}", e);
+        }
 
-        let driver = WebDriver::new("http://localhost:60103", caps).await?;
-        let driver = Arc::new(driver);
+        result
+    }
 
-        let zipcode_data = zipcode::load_zipcode_data("./zipcodetolatlong.csv");
+    /// Navigates to the office list and sweeps it until cancelled, booked or expired.
+    #[allow(clippy::too_many_arguments)]
+    async fn run_session(
+        self: &Arc<Self>,
+        driver: &WebDriver,
+        zip_code: String,
+        refresh_interval_secs: u64,
+        tx: mpsc::Sender<Vec<OfficeAvailability>>,
+        service: DMVService,
+        dates: Vec<String>,
+        tracker: &ListenerTracker,
+    ) -> ScraperResult<()> {
+        let zipcode_data = zipcode::load_zipcode_data(&self.config.zipcode_csv_path);
 
         info!("{}", zip_code);
 
-        let coordinates = zipcode_data.get(&zip_code).unwrap();
+        let coordinates = zipcode_data
+            .get(&zip_code)
+            .ok_or_else(|| ScraperError::Config(format!("unknown ZIP code {}", zip_code)))?;
 
         let latitude = coordinates.0;
         let longitude = coordinates.1;
 
-        driver.goto(BASE_URL).await?;
-
-        let grant_command = ChromeCommand::ExecuteCdpCommand(
-            "Browser.grantPermissions".to_string(),
-            json!({
-                "permissions": ["geolocation"],
-                "origin": "https://skiptheline.ncdot.gov"
-            }),
-        );
-
-        driver.cmd(grant_command).await?;
-
-        // Send Chrome DevTools Protocol command to override geolocation
-        let spoof_location_command = ChromeCommand::ExecuteCdpCommand(
-            "Page.setGeolocationOverride".to_string(),
-            json!({
-                "latitude": latitude,
-                "longitude": longitude,
-                "accuracy": 100.0
-            }),
-        );
-
-        driver.cmd(spoof_location_command).await?;
-
-        // Initial navigation
-        driver
-            .find(By::Id(BUTTON_MAKE_APPT_ID))
-            .await?
-            .click()
-            .await?;
-
-        sleep(Duration::from_secs(10)).await;
-
-        // Wait for navigation
-        'outer: loop {
-            let elements = driver.find_all(By::Css("div.form-control-child")).await?;
-            for elem in elements {
-                if elem.text().await?.contains(&selector) {
-                    if elem.is_clickable().await? {
-                        elem.click().await?;
-                        break 'outer;
-                    }
-                }
-            }
+        let selectors = selectors::active(&self.config);
+        let opened: ScraperResult<()> = async {
+            driver.goto(&selectors.base_url).await?;
+            spoof_location(driver, &selectors, latitude, longitude).await
+        }
+        .await;
+        if let Err(e) = opened {
+            artifacts::capture_failure(driver, &self.config, &tracker.request_id(), &e.to_string())
+                .await;
+            return Err(e);
         }
 
-        sleep(Duration::from_secs(10)).await;
-
-        // Now that we're on the results page, start checking periodically
+        // Each sweep navigates to the office list itself; start checking periodically
         let mut refresh_interval = interval(Duration::from_secs(refresh_interval_secs));
 
-        sleep(Duration::from_secs(1)).await;
-
+        let mut consecutive_errors = 0;
         loop {
-            refresh_interval.tick().await;
-
-            let scraper = Arc::clone(&self);
+            tokio::select! {
+                biased;
+                _ = tracker.cancellation().cancelled() => break,
+                _ = refresh_interval.tick() => {}
+            }
 
-            match scraper
-                .scrape_and_check_available_dates(&driver, &dates)
+            let expired = Self::latest_date(dates.clone())
                 .await
+                .and_then(|d| NaiveDate::parse_from_str(&d, "%Y-%m-%d").ok())
+                .is_none_or(|latest| latest < Local::now().date_naive());
+            if expired {
+                info!("All requested dates have passed, stopping listener");
+                tracker.set_phase(ListenerPhase::Expired);
+                break;
+            }
+
+            tracker.set_phase(ListenerPhase::Scanning);
+            // Picked up per sweep so a selector hotfix applies without a restart.
+            let portal = WebDriverPortal::new(driver, selectors::active(&self.config))
+                .capturing_failures(&self.config, tracker.request_id());
+            match scrape_and_check_available_dates(
+                &portal,
+                self.as_ref(),
+                &service,
+                &dates,
+                self.max_distance,
+                tracker,
+                self.is_dry_run(),
+            )
+            .await
             {
                 Ok(results) => {
+                    consecutive_errors = 0;
+                    tracker.record_scrape(&results);
                     if tx.send(results).await.is_err() {
                         // Channel closed, receiver dropped
                         break;
                     }
-                }
-                Err(e) => {
-                    error!("Error scraping page: {:?}", e);
-                }
-            }
-
-            // Refresh the page for new data
-            if let Err(e) = driver.refresh().await {
-                error!("Failed to refresh page: {:?}", e);
-                break;
-            }
-
-            // Wait for page to stabilize after refresh
-            sleep(Duration::from_secs(1)).await;
-        }
-
-        info!("Quitting Chrome session");
-
-        if let Ok(driver_inner) = Arc::try_unwrap(driver) {
-            driver_inner.quit().await?;
-        } else {
-            error!("Driver Arc still has other references — cannot quit cleanly");
-        }
-
-        Ok(())
-    }
-
-    async fn scrape_and_check_available_dates(
-        self: Arc<Self>,
-        driver: &WebDriver,
-        dates: &Vec<String>,
-    ) -> WebDriverResult<Vec<OfficeAvailability>> {
-        let mut results = Vec::new();
-
-        // Find all office elements
-        let office_elements = driver
-            .find_all(By::Css(&format!(".{}", DMV_ITEM_CLASS)))
-            .await?;
-
-        for office_el in office_elements {
-            // Get office classes to check if reservable
-            let classes = office_el.class_name().await?.unwrap_or_default();
-            let is_reservable = classes.contains(ACTIVE_UNIT_CLASS);
-
-            // Get the office name
-            let office_divs = office_el.find_all(By::Tag("div")).await?;
-            let mut office_name = String::new();
-            if office_divs.len() > 1 {
-                office_name = office_divs[1].text().await?.trim().to_string();
-            }
-
-            // Get the address
-            let addr_el = office_el
-                .find(By::Css(&format!(".{}", DMV_CHILD_CLASS)))
-                .await?;
-            let addr = addr_el.text().await?.trim().to_string();
-
-            // Extract zip code from address
-            let zip_regex = Regex::new(r"\b\d{5}\b").unwrap();
-            let zip_code = zip_regex
-                .find(&addr)
-                .map(|m| m.as_str().to_string())
-                .unwrap_or_default();
-
-            // Extract street address
-            let street_address = addr
-                .replace(&zip_code, "")
-                .trim()
-                .trim_end_matches(',')
-                .to_string();
-
-            let distance: u16 = match office_divs.iter().rev().next() {
-                Some(div) => {
-                    let text = div
-                        .text()
-                        .await
-                        .unwrap_or_default()
-                        .replace(" Miles", "")
-                        .replace("text=", "");
-                    let parsed_distance =
-                        text.parse::<f32>().expect("failed to parse the distance");
-                    parsed_distance.round() as u16
-                }
-                None => 0,
-            };
-
-            if distance > self.max_distance {
-                continue;
-            }
-            let mut office_availability = OfficeAvailability {
-                is_reservable,
-                office_name,
-                street_address,
-                zip_code,
-                distance,
-                available_dates: Vec::new(),
-                selected_date: None,
-            };
-
-            if FALSLEY_ENABLED_LOCATIONS
-                .lock()
-                .unwrap()
-                .contains(&office_availability.office_name)
-                && is_reservable
-            {
-                info!("{:?}", *FALSLEY_ENABLED_LOCATIONS.lock().unwrap());
-                continue; // skip
-            }
-
-            if is_reservable {
-                info!(
-                    "checking office {} as it appears reservable",
-                    office_availability.office_name
-                );
-                if let Ok(_) = office_el.click().await {
-                    // Wait for calendar to load
-                    sleep(Duration::from_secs(3)).await;
-
-                    // Get the month shown in the calendar
-                    let month_text = match driver.find(By::Css(".ui-datepicker-month")).await {
-                        Ok(el) => match el.text().await {
-                            Ok(text) => text,
-                            Err(_) => String::new(),
-                        },
-                        Err(_) => String::new(),
-                    };
-
-                    // Get year shown in the calendar
-                    let year_text = match driver.find(By::Css(".ui-datepicker-year")).await {
-                        Ok(el) => match el.text().await {
-                            Ok(text) => text,
-                            Err(_) => String::new(),
-                        },
-                        Err(_) => String::new(),
-                    };
-
-                    let year = year_text
-                        .parse::<i32>()
-                        .unwrap_or_else(|_| Local::now().year());
-
-                    // Find all available dates (with the active class)
-                    if let Ok(date_elements) = driver
-                        .find_all(By::Css(&format!(
-                            "a.{}",
-                            AVAILABLE_DATE_CLASS.replace(" ", ".")
-                        )))
-                        .await
-                    {
-                        for date_el in date_elements {
-                            if let Ok(day_text) = date_el.text().await {
-                                if let Ok(day) = day_text.parse::<u32>() {
-                                    // Convert month name to month number (1-12)
-                                    let month = match month_text.as_str() {
-                                        "January" => 1,
-                                        "February" => 2,
-                                        "March" => 3,
-                                        "April" => 4,
-                                        "May" => 5,
-                                        "June" => 6,
-                                        "July" => 7,
-                                        "August" => 8,
-                                        "September" => 9,
-                                        "October" => 10,
-                                        "November" => 11,
-                                        "December" => 12,
-                                        _ => continue, // This case is unexpected.
-                                    };
-
-                                    if let Some(date) = NaiveDate::from_ymd_opt(year, month, day) {
-                                        office_availability.available_dates.push(date);
-                                    }
-                                }
-                            }
-                        }
-                    }
-
-                    // --- Modification: Select the soonest date from the user-provided list ---
-                    if !office_availability.available_dates.is_empty() {
-                        // Convert provided date strings to NaiveDate objects.
-                        let provided_dates: Vec<NaiveDate> = dates
-                            .iter()
-                            .filter_map(|d| NaiveDate::parse_from_str(d, "%Y-%m-%d").ok())
-                            .collect();
-
-                        // Filter the scraped available dates for those that also appear in the user's list.
-                        let mut matching_dates: Vec<NaiveDate> = office_availability
-                            .available_dates
-                            .iter()
-                            .filter(|date| provided_dates.contains(date))
-                            .copied()
-                            .collect();
-
-                        if matching_dates.is_empty() {
-                            if let Ok(back_button) = driver.find(By::Id("BackButton")).await {
-                                let _ = back_button.click().await;
-                                sleep(Duration::from_secs(1)).await;
-                            }
-                            FALSLEY_ENABLED_LOCATIONS
-                                .lock()
-                                .unwrap()
-                                .push(office_availability.office_name);
-
-                            break;
-                        }
-
-                        // Sort the matching dates so that the soonest is first.
-                        matching_dates.sort();
-
-                        if let Some(earliest_date) = matching_dates.first() {
-                            // Get the day as text.
-                            let day_text = earliest_date.day().to_string();
-
-                            // Try to find and click the date on the page.
-                            if driver.find(By::LinkText(&day_text)).await.is_ok() {
-                                info!(
-                                    "Selected date {} for office {}",
-                                    earliest_date, office_availability.office_name
-                                );
-                            }
-                        }
-                    }
-                    // ------------------------------------------------------------------------
-
-                    if let Ok(next_button) = driver.find(By::ClassName("next-button")).await {
-                        let _ = next_button.click().await;
-                        sleep(Duration::from_secs(1)).await;
-                    }
-
-                    // Check for various "no availability" messages and back out if needed.
-                    if driver
-                        .find(By::Tag("body"))
-                        .await
-                        .expect("failed to read pages text")
-                        .text()
-                        .await
-                        .unwrap()
-                        .contains("This office does not currently have any appointments available in the next 90 days. Please try scheduling an appointment at another office or try again tomorrow when a new day's appointments will be available.")
-                    {
-                        if let Ok(back_button) = driver.find(By::Id("BackButton")).await {
-                            let _ = back_button.click().await;
-                            sleep(Duration::from_secs(1)).await;
-                        }
-                        FALSLEY_ENABLED_LOCATIONS.lock().unwrap().push(office_availability.office_name);
+                    if tracker.snapshot().phase == ListenerPhase::Booked {
+                        info!("Appointment booked, stopping listener");
                         break;
                     }
-
-                    if driver
-                        .find(By::Tag("body"))
-                        .await
-                        .expect("failed to read pages text")
-                        .text()
-                        .await
-                        .unwrap()
-                        .contains("Please select a date and time to continue.")
+                }
+                Err(e) => {
+                    error!("Error scraping page: {}", e);
+                    portal.capture(&e.to_string()).await;
+                    consecutive_errors += 1;
+                    if e.ends_session()
+                        || e.recovery() == Recovery::Fail
+                        || consecutive_errors >= MAX_CONSECUTIVE_ERRORS
                     {
-                        if let Ok(back_button) = driver.find(By::Id("BackButton")).await {
-                            let _ = back_button.click().await;
-                            sleep(Duration::from_secs(1)).await;
-                        }
-                        FALSLEY_ENABLED_LOCATIONS
-                            .lock()
-                            .unwrap()
-                            .push(office_availability.office_name);
-                        break;
+                        return Err(e);
                     }
-
-                    if driver
-                        .find(By::Tag("body"))
-                        .await
-                        .expect("failed to read pages text")
-                        .text()
-                        .await
-                        .unwrap()
-                        .contains("We were unable")
+                    tracker.record_error(&e);
+                    if e.recovery() == Recovery::BackOff
+                        && !tracker.sleep(backoff_delay(consecutive_errors)).await
                     {
-                        if let Ok(back_button) = driver.find(By::Id("BackButton")).await {
-                            let _ = back_button.click().await;
-                            sleep(Duration::from_secs(1)).await;
-                        }
-                        FALSLEY_ENABLED_LOCATIONS
-                            .lock()
-                            .unwrap()
-                            .push(office_availability.office_name);
                         break;
                     }
-
-                    sleep(Duration::from_secs(3)).await;
-
-                    let name_clone = self.name.clone();
-                    let names: Vec<&str> = name_clone.split('_').collect();
-                    let fname = names[0];
-                    let lname = if names.len() > 1 { names[1] } else { "" };
-
-                    driver.find(By::Id(FNAME_INPUT_ID)).await?.click().await?;
-                    sleep(Duration::from_millis(150)).await;
-                    driver
-                        .find(By::Id(FNAME_INPUT_ID))
-                        .await?
-                        .send_keys(fname)
-                        .await?;
-
-                    sleep(Duration::from_millis(150)).await;
-                    driver.find(By::Id(LNAME_INPUT_ID)).await?.click().await?;
-                    sleep(Duration::from_millis(150)).await;
-                    driver
-                        .find(By::Id(LNAME_INPUT_ID))
-                        .await?
-                        .send_keys(lname)
-                        .await?;
-
-                    sleep(Duration::from_millis(150)).await;
-                    driver
-                        .find(By::Id(PHONE_NUM_INPUT_ID))
-                        .await?
-                        .click()
-                        .await?;
-                    sleep(Duration::from_millis(150)).await;
-                    driver
-                        .find(By::Id(PHONE_NUM_INPUT_ID))
-                        .await?
-                        .send_keys(self.phone_number.as_str())
-                        .await?;
-
-                    sleep(Duration::from_millis(150)).await;
-                    driver.find(By::Id(EMAIL_INPUT_ID)).await?.click().await?;
-                    sleep(Duration::from_millis(150)).await;
-
-                    let last_date = Self::latest_date(dates.clone()).await.unwrap();
-                    let proxy_email = Self::register_proxy_email(
-                        &self.email,
-                        &last_date,
-                        "http://localhost:8000",
-                    )
-                    .await
-                    .unwrap();
-
-                    driver
-                        .find(By::Id(EMAIL_INPUT_ID))
-                        .await?
-                        .send_keys(&proxy_email)
-                        .await?;
-
-                    sleep(Duration::from_millis(150)).await;
-                    driver
-                        .find(By::Id(CONFIRM_EMAIL_INPUT_ID))
-                        .await?
-                        .click()
-                        .await?;
-                    sleep(Duration::from_millis(150)).await;
-                    driver
-                        .find(By::Id(CONFIRM_EMAIL_INPUT_ID))
-                        .await?
-                        .send_keys(proxy_email)
-                        .await?;
-
-                    info!("solving captcha");
-                    dotenv().ok();
-                    let key = std::env::var("TWOCAPTCHA_KEY").expect("no 2captcha key set");
-                    let solver = CaptchaSolver::new(key);
-
-                    let args = RecaptchaV2::builder()
-                        .website_url("https://skiptheline.ncdot.gov/")
-                        .website_key("6LegSQ0dAAAAALO2_3-EDnTRDc7AQLz6Jo1BFyct")
-                        .build()
-                        .expect("failed to solve captcha");
-
-                    let solution = solver
-                        .solve(args)
-                        .await
-                        .expect("failed to solve captcha...")
-                        .unwrap()
-                        .solution;
-
-                    let token = solution.g_recaptcha_response;
-
-                    info!("got solution sucessfully!");
-
-                    info!("{}", token);
-
-                    let js = r#"
-                        document.getElementById('g-recaptcha-response').innerHTML = arguments[0];
-                        document.getElementById('g-recaptcha-response').style.display = 'block';
-                    "#;
-
-                    info!("executing js for captcha");
-
-                    let args: Vec<Value> = vec![Value::String(token.to_string())];
-                    driver.execute(js, Arc::from(args)).await?;
-
-                    let js_callback = r#"
-                        CaptchaCallBack(arguments[0]);
-                    "#;
-
-                    driver
-                        .execute(
-                            js_callback,
-                            Arc::from(vec![Value::String(token.to_string())]),
-                        )
-                        .await?;
-
-                    sleep(Duration::from_secs(1)).await;
-
-                    if let Ok(next_button) = driver.find(By::ClassName("next-button")).await {
-                        let _ = next_button.click().await;
-                        sleep(Duration::from_secs(1)).await;
-                    }
-
-                    if let Ok(next_button) = driver.find(By::ClassName("next-button")).await {
-                        let _ = next_button.click().await;
-                        sleep(Duration::from_secs(1)).await;
-                    }
-
-                    break;
-                }
-            } else {
-                if FALSLEY_ENABLED_LOCATIONS
-                    .lock()
-                    .unwrap()
-                    .contains(&office_availability.office_name)
-                {
-                    info!("clearing locations...");
-                    FALSLEY_ENABLED_LOCATIONS
-                        .lock()
-                        .unwrap()
-                        .retain(|x| x != &office_availability.office_name);
                 }
             }
 
-            results.push(office_availability);
+            // Refresh the page for new data
+            driver.refresh().await?;
         }
 
-        Ok(results)
+        Ok(())
     }
 
     async fn latest_date(dates: Vec<String>) -> Option<String> {
@@ -632,7 +383,7 @@ impl NCDMVScraper {
         real_email: &str,
         expire_date: &str,
         api_url: &str,
-    ) -> Result<String, Box<dyn Error>> {
+    ) -> ScraperResult<String> {
         let client = Client::new();
         let body = RegisterRequest {
             real_email,
@@ -643,14 +394,21 @@ impl NCDMVScraper {
             .post(format!("{}/register", api_url))
             .json(&body)
             .send()
-            .await?;
+            .await
+            .map_err(|e| ScraperError::EmailProxy(e.to_string()))?;
 
         if res.status().is_success() {
-            let parsed: crate::models::email::RegisterResponse = res.json().await?;
+            let parsed: crate::models::email::RegisterResponse = res
+                .json()
+                .await
+                .map_err(|e| ScraperError::EmailProxy(e.to_string()))?;
             Ok(parsed.proxy_email)
         } else {
-            let error_text = res.text().await?;
-            Err(format!("API error: {}", error_text).into())
+            let error_text = res.text().await.unwrap_or_default();
+            Err(ScraperError::EmailProxy(format!(
+                "API error: {}",
+                error_text
+            )))
         }
     }
 
@@ -659,6 +417,7 @@ impl NCDMVScraper {
         refresh_interval_secs: u64,
         service_type: DMVService,
         dates: Vec<String>,
+        tracker: ListenerTracker,
     ) -> mpsc::Receiver<Vec<OfficeAvailability>> {
         let (tx, rx) = mpsc::channel(117); // Buffer size of 117 for 117 DMVs in NC
         info!("scraping NC DMV data with date checking");
@@ -668,20 +427,740 @@ impl NCDMVScraper {
         let zip_code = scraper.zipcode.clone();
 
         tokio::spawn(async move {
-            if let Err(e) = scraper
-                .stream_available_appointments(
-                    zip_code,
-                    refresh_interval_secs,
-                    tx,
-                    service_type.selector().to_string(),
-                    dates,
-                )
-                .await
-            {
-                error!("Error starting appointment stream: {:?}", e);
+            let mut restarts = 0;
+            loop {
+                let last_scrape_at = tracker.snapshot().last_scrape_at;
+                let Err(e) = scraper
+                    .clone()
+                    .stream_available_appointments(
+                        zip_code.clone(),
+                        refresh_interval_secs,
+                        tx.clone(),
+                        service_type,
+                        dates.clone(),
+                        tracker.clone(),
+                    )
+                    .await
+                else {
+                    break;
+                };
+
+                // A session that managed to scrape resets the restart budget.
+                if tracker.snapshot().last_scrape_at != last_scrape_at {
+                    restarts = 0;
+                }
+                restarts += 1;
+
+                if e.recovery() == Recovery::Fail || restarts > MAX_SESSION_RESTARTS {
+                    error!("Appointment stream failed: {}", e);
+                    tracker.fail(&e);
+                    break;
+                }
+
+                error!("Appointment stream failed, restarting session: {}", e);
+                tracker.record_error(&e);
+                if !tracker.sleep(backoff_delay(restarts)).await {
+                    break;
+                }
             }
         });
 
         rx
     }
 }
+
+#[async_trait]
+impl BookingServices for NCDMVScraper {
+    async fn contact_details(&self, dates: &[String]) -> ScraperResult<ContactDetails> {
+        let names: Vec<&str> = self.name.split('_').collect();
+        let first_name = names[0].to_string();
+        let last_name = names.get(1).copied().unwrap_or_default().to_string();
+
+        let last_date = Self::latest_date(dates.to_vec())
+            .await
+            .ok_or_else(|| ScraperError::Config("request has no valid dates".to_string()))?;
+        let email =
+            Self::register_proxy_email(&self.email, &last_date, &self.config.email_proxy_url)
+                .await?;
+
+        Ok(ContactDetails {
+            first_name,
+            last_name,
+            phone_number: self.phone_number.clone(),
+            email,
+        })
+    }
+
+    async fn captcha_token(&self) -> ScraperResult<String> {
+        info!("solving captcha");
+        let key = self
+            .config
+            .twocaptcha_key
+            .clone()
+            .ok_or_else(|| ScraperError::Config("twocaptcha_key is not set".to_string()))?;
+        let solver = CaptchaSolver::new(key);
+        let selectors = selectors::active(&self.config);
+
+        let args = RecaptchaV2::builder()
+            .website_url(selectors.base_url.as_str())
+            .website_key(&self.config.captcha_site_key)
+            .build()
+            .map_err(|e| ScraperError::Captcha(e.to_string()))?;
+
+        let solution = solver
+            .solve(args)
+            .await
+            .map_err(|e| ScraperError::Captcha(e.to_string()))?
+            .ok_or_else(|| ScraperError::Captcha("no solution returned".to_string()))?
+            .solution;
+
+        info!("got solution sucessfully!");
+
+        Ok(solution.g_recaptcha_response.to_string())
+    }
+}
+
+/// Reads the office list and tries to book the first reservable office with a
+/// matching date. A dry run reads every reservable office's calendar instead and
+/// reports matches through `selected_date`.
+async fn scrape_and_check_available_dates(
+    portal: &dyn AppointmentPortal,
+    services: &dyn BookingServices,
+    service: &DMVService,
+    dates: &[String],
+    max_distance: u16,
+    tracker: &ListenerTracker,
+    dry_run: bool,
+) -> ScraperResult<Vec<OfficeAvailability>> {
+    let mut results = Vec::new();
+
+    for office in portal.list_offices(service).await? {
+        if office.distance > max_distance {
+            continue;
+        }
+        let is_reservable = office.is_reservable;
+        let mut office_availability = office.into_availability(service, tracker.request_id());
+
+        // Dry runs monitor availability, so they re-read falsely enabled offices too.
+        if !dry_run
+            && is_reservable
+            && FALSLEY_ENABLED_LOCATIONS
+                .lock()
+                .unwrap()
+                .contains(&office_availability.office_name)
+        {
+            info!("{:?}", *FALSLEY_ENABLED_LOCATIONS.lock().unwrap());
+            continue; // skip
+        }
+
+        if is_reservable {
+            // Don't start a booking for a listener that is being stopped.
+            if tracker.is_cancelled() {
+                break;
+            }
+            info!(
+                "checking office {} as it appears reservable",
+                office_availability.office_name
+            );
+            let outcome = book_office(
+                portal,
+                services,
+                &mut office_availability,
+                dates,
+                tracker,
+                dry_run,
+            )
+            .await?;
+            match outcome {
+                OfficeOutcome::Booked => tracker.set_phase(ListenerPhase::Booked),
+                OfficeOutcome::Matched => info!(
+                    "Dry run: office {} offers {:?}, not booking",
+                    office_availability.office_name, office_availability.selected_date
+                ),
+                OfficeOutcome::Unavailable if dry_run => {}
+                OfficeOutcome::Unavailable => FALSLEY_ENABLED_LOCATIONS
+                    .lock()
+                    .unwrap()
+                    .push(office_availability.office_name.clone()),
+            }
+            results.push(office_availability);
+            // Only one office is booked per sweep; the list is stale afterwards.
+            // Dry runs come straight back to the list and read every calendar.
+            if dry_run {
+                continue;
+            }
+            break;
+        } else if FALSLEY_ENABLED_LOCATIONS
+            .lock()
+            .unwrap()
+            .contains(&office_availability.office_name)
+        {
+            info!("clearing locations...");
+            FALSLEY_ENABLED_LOCATIONS
+                .lock()
+                .unwrap()
+                .retain(|x| x != &office_availability.office_name);
+        }
+
+        results.push(office_availability);
+    }
+
+    Ok(results)
+}
+
+/// Walks an office through calendar, time selection, contact form, captcha and
+/// confirmation, backing out as soon as the office can't be booked. A dry run
+/// backs out of the calendar once it has found a matching date, so no slot is
+/// ever selected and the contact form is never reached.
+async fn book_office(
+    portal: &dyn AppointmentPortal,
+    services: &dyn BookingServices,
+    office: &mut OfficeAvailability,
+    dates: &[String],
+    tracker: &ListenerTracker,
+    dry_run: bool,
+) -> ScraperResult<OfficeOutcome> {
+    let mut page = portal.open_office(&office.office_name).await?;
+
+    loop {
+        info!("Office {} is on {:?}", office.office_name, page);
+        // The portal can skip the calendar; a dry run must not act on later steps.
+        if dry_run && matches!(page, PageState::TimeSelection | PageState::ContactForm) {
+            info!(
+                "Dry run: leaving office {} on {:?}",
+                office.office_name, page
+            );
+            portal.back_to_office_list(&page).await?;
+            return Ok(if office.selected_date.is_some() {
+                OfficeOutcome::Matched
+            } else {
+                OfficeOutcome::Unavailable
+            });
+        }
+
+        page = match page {
+            PageState::Calendar => {
+                office.available_dates = portal.available_dates().await?;
+
+                let Some(date) = earliest_matching_date(&office.available_dates, dates) else {
+                    portal.back_to_office_list(&page).await?;
+                    return Ok(OfficeOutcome::Unavailable);
+                };
+
+                office.selected_date = Some(date);
+                if dry_run {
+                    portal.back_to_office_list(&page).await?;
+                    return Ok(OfficeOutcome::Matched);
+                }
+
+                info!("Selecting date {} for office {}", date, office.office_name);
+                portal.select_date(date).await?
+            }
+            PageState::TimeSelection => portal.select_time().await?,
+            PageState::ContactForm => {
+                tracker.set_phase(ListenerPhase::Booking);
+                let contact = services.contact_details(dates).await?;
+                portal.fill_contact_form(&contact).await?;
+
+                let token = services.captcha_token().await?;
+                portal.submit_captcha_token(&token).await?;
+
+                match portal.read_confirmation().await? {
+                    PageState::Confirmation => return Ok(OfficeOutcome::Booked),
+                    other => other,
+                }
+            }
+            PageState::Error(message) => {
+                // An empty calendar is routine; any other message is worth a look.
+                let unexpected = message != NO_APPOINTMENTS_MESSAGE;
+                let page = PageState::Error(message);
+                if page.recovery() == RecoveryAction::BackOff {
+                    return Err(ScraperError::PortalOutage(format!("{:?}", page)));
+                }
+                info!("Office {} is unavailable: {:?}", office.office_name, page);
+                if unexpected {
+                    portal.capture(&format!("{:?}", page)).await;
+                }
+                portal.back_to_office_list(&page).await?;
+                // A refused booking goes back to watching the other offices.
+                if tracker.snapshot().phase == ListenerPhase::Booking {
+                    tracker.set_phase(ListenerPhase::Scanning);
+                }
+                return Ok(OfficeOutcome::Unavailable);
+            }
+            other => {
+                return Err(ScraperError::DomDrift(format!(
+                    "unexpected {:?} page while booking",
+                    other
+                )));
+            }
+        };
+    }
+}
+
+/// The soonest available date that is also one of the requested dates.
+fn earliest_matching_date(available: &[NaiveDate], requested: &[String]) -> Option<NaiveDate> {
+    let requested: Vec<NaiveDate> = requested
+        .iter()
+        .filter_map(|d| NaiveDate::parse_from_str(d, "%Y-%m-%d").ok())
+        .collect();
+
+    available
+        .iter()
+        .filter(|date| requested.contains(date))
+        .min()
+        .copied()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scraping::constants::*;
+    use crate::scraping::fake_portal::{FakeBookingServices, FakePortal, ScriptedOffice};
+    use crate::scraping::fixtures::date;
+    use crate::scraping::mock_portal::{MockBooking, MockOffice, MockPortal};
+
+    fn dates(list: &[&str]) -> Vec<String> {
+        list.iter().map(|s| s.to_string()).collect()
+    }
+
+    async fn sweep(
+        portal: &FakePortal,
+        requested: &[String],
+        max_distance: u16,
+        tracker: &ListenerTracker,
+    ) -> ScraperResult<Vec<OfficeAvailability>> {
+        let service: DMVService = "first-time".parse().unwrap();
+        scrape_and_check_available_dates(
+            portal,
+            &FakeBookingServices::default(),
+            &service,
+            requested,
+            max_distance,
+            tracker,
+            false,
+        )
+        .await
+    }
+
+    #[test]
+    fn only_our_chrome_profiles_are_removed() {
+        // Another process's profile in the shared temp dir.
+        let foreign = tempfile::Builder::new()
+            .prefix(CHROME_PROFILE_PREFIX)
+            .tempdir()
+            .unwrap();
+
+        let closed = ChromeProfile::create().unwrap();
+        let closed_path = closed.path().to_path_buf();
+        drop(closed);
+        assert!(!closed_path.exists());
+
+        // A listener aborted before its profile could be removed.
+        let leftover = ChromeProfile::create().unwrap();
+        let leftover_path = leftover.path().to_path_buf();
+        std::mem::forget(leftover);
+
+        remove_chrome_profiles();
+        assert!(!leftover_path.exists());
+        assert!(foreign.path().exists());
+    }
+
+    #[tokio::test]
+    async fn books_the_earliest_requested_date() {
+        let portal = FakePortal::new(vec![
+            ScriptedOffice::closed("Booking Closed", 3),
+            ScriptedOffice::reservable(
+                "Booking Open",
+                5,
+                &[date("2030-01-02"), date("2030-01-03"), date("2030-01-04")],
+            ),
+        ]);
+        let tracker = ListenerTracker::new("booking".to_string());
+
+        let results = sweep(&portal, &dates(&["2030-01-04", "2030-01-03"]), 25, &tracker)
+            .await
+            .unwrap();
+
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[1].selected_date, Some(date("2030-01-03")));
+        assert_eq!(results[1].available_dates.len(), 3);
+        assert_eq!(tracker.snapshot().phase, ListenerPhase::Booked);
+        assert_eq!(
+            portal.calls(),
+            vec![
+                "list_offices first-time",
+                "open_office Booking Open",
+                "available_dates",
+                "select_date 2030-01-03",
+                "fill_contact_form proxy@example.com",
+                "submit_captcha_token fake-token",
+                "read_confirmation",
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn picks_a_time_slot_when_offered() {
+        let portal = FakePortal::new(vec![
+            ScriptedOffice::reservable("Slots Office", 5, &[date("2030-02-01")]).with_time_slots(),
+        ]);
+        let tracker = ListenerTracker::new("slots".to_string());
+
+        sweep(&portal, &dates(&["2030-02-01"]), 25, &tracker)
+            .await
+            .unwrap();
+
+        assert!(portal.calls().contains(&"select_time".to_string()));
+        assert_eq!(tracker.snapshot().phase, ListenerPhase::Booked);
+    }
+
+    #[tokio::test]
+    async fn backs_out_of_offices_without_requested_dates() {
+        let portal = FakePortal::new(vec![ScriptedOffice::reservable(
+            "No Match Office",
+            5,
+            &[date("2030-03-01")],
+        )]);
+        let tracker = ListenerTracker::new("no-match".to_string());
+        let requested = dates(&["2030-03-02"]);
+
+        let results = sweep(&portal, &requested, 25, &tracker).await.unwrap();
+
+        assert_eq!(results[0].selected_date, None);
+        assert_eq!(portal.page(), PageState::OfficeList);
+        assert_eq!(portal.calls().last().unwrap(), "back_to_office_list");
+        assert_ne!(tracker.snapshot().phase, ListenerPhase::Booked);
+
+        // The office is remembered as falsely reservable and skipped next sweep.
+        let results = sweep(&portal, &requested, 25, &tracker).await.unwrap();
+        assert!(results.is_empty());
+    }
+
+    #[tokio::test]
+    async fn backs_out_when_the_portal_refuses_an_office() {
+        let portal = FakePortal::new(vec![
+            ScriptedOffice::reservable("Refusing Office", 5, &[date("2030-04-01")])
+                .opens_to(PageState::Error(UNABLE_MESSAGE.to_string())),
+        ]);
+        let tracker = ListenerTracker::new("refused".to_string());
+
+        let results = sweep(&portal, &dates(&["2030-04-01"]), 25, &tracker)
+            .await
+            .unwrap();
+
+        assert_eq!(results.len(), 1);
+        assert_eq!(portal.page(), PageState::OfficeList);
+        assert_eq!(
+            portal.calls(),
+            vec![
+                "list_offices first-time",
+                "open_office Refusing Office",
+                "back_to_office_list",
+            ]
+        );
+        assert_eq!(
+            portal.captures(),
+            vec![format!(
+                "{:?}",
+                PageState::Error(UNABLE_MESSAGE.to_string())
+            )]
+        );
+    }
+
+    #[tokio::test]
+    async fn empty_calendars_are_not_captured() {
+        let portal = FakePortal::new(vec![
+            ScriptedOffice::reservable("Empty Office", 5, &[date("2030-04-02")])
+                .opens_to(PageState::Error(NO_APPOINTMENTS_MESSAGE.to_string())),
+        ]);
+        let tracker = ListenerTracker::new("empty".to_string());
+
+        sweep(&portal, &dates(&["2030-04-02"]), 25, &tracker)
+            .await
+            .unwrap();
+
+        assert!(portal.captures().is_empty());
+    }
+
+    #[tokio::test]
+    async fn backs_out_when_the_booking_is_refused() {
+        let portal = FakePortal::new(vec![
+            ScriptedOffice::reservable("Taken Office", 5, &[date("2030-05-01")])
+                .submits_to(PageState::Error(UNABLE_MESSAGE.to_string())),
+        ]);
+        let tracker = ListenerTracker::new("taken".to_string());
+
+        sweep(&portal, &dates(&["2030-05-01"]), 25, &tracker)
+            .await
+            .unwrap();
+
+        assert_eq!(tracker.snapshot().phase, ListenerPhase::Scanning);
+        assert_eq!(portal.page(), PageState::OfficeList);
+        assert_eq!(portal.captures().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn outage_pages_are_errors() {
+        let portal = FakePortal::new(vec![
+            ScriptedOffice::reservable("Outage Office", 5, &[date("2030-06-01")])
+                .opens_to(PageState::Error(PORTAL_OUTAGE_MESSAGES[0].to_string())),
+        ]);
+        let tracker = ListenerTracker::new("outage".to_string());
+
+        let result = sweep(&portal, &dates(&["2030-06-01"]), 25, &tracker).await;
+
+        assert!(matches!(result, Err(ScraperError::PortalOutage(_))));
+    }
+
+    #[tokio::test]
+    async fn skips_offices_beyond_max_distance() {
+        let portal = FakePortal::new(vec![
+            ScriptedOffice::reservable("Far Office", 40, &[date("2030-07-01")]),
+            ScriptedOffice::closed("Near Office", 10),
+        ]);
+        let tracker = ListenerTracker::new("distance".to_string());
+
+        let results = sweep(&portal, &dates(&["2030-07-01"]), 25, &tracker)
+            .await
+            .unwrap();
+
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].office_name, "Near Office");
+        assert_eq!(portal.calls(), vec!["list_offices first-time"]);
+    }
+
+    #[tokio::test]
+    async fn dry_runs_never_fill_in_a_form_the_portal_skips_to() {
+        let portal = FakePortal::new(vec![
+            ScriptedOffice::reservable("Form Office", 5, &[date("2030-08-01")])
+                .opens_to(PageState::ContactForm),
+            ScriptedOffice::reservable("Slot Office", 6, &[date("2030-08-01")])
+                .opens_to(PageState::TimeSelection),
+        ]);
+        let services = FakeBookingServices::default();
+        let tracker = ListenerTracker::new("dry-run-form".to_string());
+        let service: DMVService = "first-time".parse().unwrap();
+
+        let results = scrape_and_check_available_dates(
+            &portal,
+            &services,
+            &service,
+            &dates(&["2030-08-01"]),
+            25,
+            &tracker,
+            true,
+        )
+        .await
+        .unwrap();
+
+        assert!(services.calls().is_empty());
+        assert!(results.iter().all(|office| office.selected_date.is_none()));
+        assert_eq!(portal.page(), PageState::OfficeList);
+        assert!(!portal.calls().iter().any(|call| {
+            call.starts_with("select_time")
+                || call.starts_with("fill_contact_form")
+                || call.starts_with("submit_captcha_token")
+        }));
+        assert_ne!(tracker.snapshot().phase, ListenerPhase::Booking);
+    }
+
+    #[tokio::test]
+    async fn dry_runs_read_every_calendar_without_booking() {
+        let portal = FakePortal::new(vec![
+            ScriptedOffice::reservable("Dry Match", 5, &[date("2030-08-01"), date("2030-08-02")]),
+            ScriptedOffice::closed("Dry Closed", 6),
+            ScriptedOffice::reservable("Dry No Match", 7, &[date("2030-08-05")]),
+            ScriptedOffice::reservable("Dry Second Match", 8, &[date("2030-08-02")]),
+        ]);
+        let tracker = ListenerTracker::new("dry-run".to_string());
+        let service: DMVService = "first-time".parse().unwrap();
+        let requested = dates(&["2030-08-02"]);
+
+        let results = scrape_and_check_available_dates(
+            &portal,
+            &FakeBookingServices::default(),
+            &service,
+            &requested,
+            25,
+            &tracker,
+            true,
+        )
+        .await
+        .unwrap();
+
+        let matches: Vec<Option<NaiveDate>> = results.iter().map(|o| o.selected_date).collect();
+        assert_eq!(
+            matches,
+            vec![
+                Some(date("2030-08-02")),
+                None,
+                None,
+                Some(date("2030-08-02"))
+            ]
+        );
+        assert_eq!(results[2].available_dates, vec![date("2030-08-05")]);
+        assert_eq!(portal.page(), PageState::OfficeList);
+        assert!(
+            !portal.calls().iter().any(
+                |call| call.starts_with("select_date") || call.starts_with("fill_contact_form")
+            )
+        );
+        assert!(!matches!(
+            tracker.snapshot().phase,
+            ListenerPhase::Booking | ListenerPhase::Booked
+        ));
+
+        // Offices without a match are not remembered as falsely reservable.
+        let results = scrape_and_check_available_dates(
+            &portal,
+            &FakeBookingServices::default(),
+            &service,
+            &requested,
+            25,
+            &tracker,
+            true,
+        )
+        .await
+        .unwrap();
+        assert_eq!(results.len(), 4);
+    }
+
+    // End-to-end runs against the mock portal. They need a local chromedriver:
+    // `chromedriver --port=9515 &` then
+    // `WEBDRIVER_URL=http://localhost:9515 cargo test -- --ignored`.
+
+    fn mock_config(mock: &MockPortal) -> Arc<AppConfig> {
+        Arc::new(AppConfig {
+            webdriver_url: std::env::var("WEBDRIVER_URL")
+                .unwrap_or_else(|_| "http://localhost:9515".to_string()),
+            selectors: SelectorProfile {
+                base_url: mock.portal_url(),
+                ..SelectorProfile::default()
+            },
+            email_proxy_url: mock.base_url().to_string(),
+            ..AppConfig::default()
+        })
+    }
+
+    async fn mock_scraper(config: Arc<AppConfig>) -> NCDMVScraper {
+        NCDMVScraper::new(
+            config,
+            "27610".to_string(),
+            25,
+            "Test_User".to_string(),
+            "9195550100".to_string(),
+            "user@example.com".to_string(),
+        )
+        .await
+        .unwrap()
+    }
+
+    /// The scraper's own contact details, through the mock email proxy, with a
+    /// made-up captcha token.
+    struct MockCaptcha(NCDMVScraper);
+
+    #[async_trait]
+    impl BookingServices for MockCaptcha {
+        async fn contact_details(&self, dates: &[String]) -> ScraperResult<ContactDetails> {
+            self.0.contact_details(dates).await
+        }
+
+        async fn captcha_token(&self) -> ScraperResult<String> {
+            Ok("mock-token".to_string())
+        }
+    }
+
+    #[actix_web::test]
+    #[ignore = "needs a local chromedriver"]
+    async fn books_on_the_mock_portal() {
+        let mock = MockPortal::start(vec![
+            MockOffice::new("Garner", "1240 US-70, Garner, NC 27529", 7.8),
+            MockOffice::new("Raleigh West", "4121 New Bern Ave, Raleigh, NC 27610", 3.2)
+                .with_dates(&[date("2030-01-04"), date("2030-01-29")])
+                .with_times(&["8:00 AM", "8:15 AM"]),
+        ])
+        .await
+        .unwrap();
+        let config = mock_config(&mock);
+        let services = MockCaptcha(mock_scraper(config.clone()).await);
+        let service: DMVService = "first-time".parse().unwrap();
+        let tracker = ListenerTracker::new("mock-booking".to_string());
+
+        let (driver, _profile_dir) = start_chrome(&config).await.unwrap();
+        driver.goto(&config.selectors.base_url).await.unwrap();
+        let result = scrape_and_check_available_dates(
+            &WebDriverPortal::new(&driver, Arc::new(config.selectors.clone())),
+            &services,
+            &service,
+            &dates(&["2030-01-29"]),
+            25,
+            &tracker,
+            false,
+        )
+        .await;
+        driver.quit().await.unwrap();
+
+        let results = result.unwrap();
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[1].selected_date, Some(date("2030-01-29")));
+        assert_eq!(tracker.snapshot().phase, ListenerPhase::Booked);
+        assert_eq!(mock.state.registered_emails(), vec!["user@example.com"]);
+        assert_eq!(
+            mock.state.bookings(),
+            vec![MockBooking {
+                office: "Raleigh West".to_string(),
+                date: date("2030-01-29"),
+                time: "8:00 AM".to_string(),
+                first_name: "Test".to_string(),
+                last_name: "User".to_string(),
+                phone_number: "9195550100".to_string(),
+                email: "proxy-1@mock.test".to_string(),
+                confirm_email: "proxy-1@mock.test".to_string(),
+                captcha_token: "mock-token".to_string(),
+            }]
+        );
+        mock.stop().await;
+    }
+
+    #[actix_web::test]
+    #[ignore = "needs a local chromedriver"]
+    async fn streams_offices_from_the_mock_portal() {
+        let mock = MockPortal::start(vec![MockOffice::new(
+            "Stream Office",
+            "4121 New Bern Ave, Raleigh, NC 27610",
+            3.2,
+        )])
+        .await
+        .unwrap();
+        let scraper = Arc::new(mock_scraper(mock_config(&mock)).await);
+        let service: DMVService = "first-time".parse().unwrap();
+        let tracker = ListenerTracker::new("mock-stream".to_string());
+
+        let mut receiver = scraper
+            .start_appointment_stream(1, service, dates(&["2030-01-29"]), tracker.clone())
+            .await;
+
+        let first = receiver.recv().await.unwrap();
+        assert_eq!(first[0].office_name, "Stream Office");
+        assert!(!first[0].is_reservable);
+
+        // A slot opening up between sweeps shows on the next one.
+        mock.state.set_offices(vec![
+            MockOffice::new("Stream Office", "4121 New Bern Ave, Raleigh, NC 27610", 3.2)
+                .with_dates(&[date("2030-01-04")]),
+        ]);
+        let second = loop {
+            let batch = receiver.recv().await.unwrap();
+            if batch[0].is_reservable {
+                break batch;
+            }
+        };
+        assert!(second[0].is_reservable);
+        assert_eq!(second[0].available_dates, vec![date("2030-01-04")]);
+
+        tracker.cancellation().cancel();
+        while receiver.recv().await.is_some() {}
+        mock.stop().await;
+    }
+}
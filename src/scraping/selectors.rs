@@ -0,0 +1,198 @@
+use once_cell::sync::Lazy;
+use scraper::Selector;
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, RwLock};
+
+use crate::config::AppConfig;
+use crate::models::appointment::FieldError;
+use crate::scraping::constants::*;
+
+/// Where the portal lives and how its elements are found. Defaults are the values
+/// in `scraping::constants`; the `[selectors]` table of the config file overrides
+/// them, and a hotfix set through `/admin/selectors` overrides both until restart.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SelectorProfile {
+    /// Start page of the appointment portal.
+    pub base_url: String,
+    pub make_appointment_button_id: String,
+    /// Office entries on the office list.
+    pub office_item_class: String,
+    /// Service tiles and office addresses.
+    pub item_child_class: String,
+    /// Marks an office entry as bookable.
+    pub active_unit_class: String,
+    /// Space-separated classes of a bookable datepicker day.
+    pub available_date_class: String,
+    pub datepicker_class: String,
+    pub time_slot_class: String,
+    pub back_button_id: String,
+    pub next_button_class: String,
+    pub recaptcha_response_id: String,
+    pub first_name_input_id: String,
+    pub last_name_input_id: String,
+    pub phone_number_input_id: String,
+    pub email_input_id: String,
+    pub confirm_email_input_id: String,
+}
+
+impl Default for SelectorProfile {
+    fn default() -> Self {
+        SelectorProfile {
+            base_url: BASE_URL.to_string(),
+            make_appointment_button_id: BUTTON_MAKE_APPT_ID.to_string(),
+            office_item_class: DMV_ITEM_CLASS.to_string(),
+            item_child_class: DMV_CHILD_CLASS.to_string(),
+            active_unit_class: ACTIVE_UNIT_CLASS.to_string(),
+            available_date_class: AVAILABLE_DATE_CLASS.to_string(),
+            datepicker_class: DATEPICKER_CLASS.to_string(),
+            time_slot_class: TIME_SLOT_CLASS.to_string(),
+            back_button_id: BACK_BUTTON_ID.to_string(),
+            next_button_class: NEXT_BUTTON_CLASS.to_string(),
+            recaptcha_response_id: RECAPTCHA_RESPONSE_ID.to_string(),
+            first_name_input_id: FNAME_INPUT_ID.to_string(),
+            last_name_input_id: LNAME_INPUT_ID.to_string(),
+            phone_number_input_id: PHONE_NUM_INPUT_ID.to_string(),
+            email_input_id: EMAIL_INPUT_ID.to_string(),
+            confirm_email_input_id: CONFIRM_EMAIL_INPUT_ID.to_string(),
+        }
+    }
+}
+
+/// CSS selector for an element with `id`.
+pub fn id_css(id: &str) -> String {
+    format!("#{}", id)
+}
+
+/// CSS selector for an element with all of the space-separated `classes`.
+pub fn class_css(classes: &str) -> String {
+    classes
+        .split_whitespace()
+        .map(|class| format!(".{}", class))
+        .collect()
+}
+
+impl SelectorProfile {
+    /// Every ID and class in the profile with its field name, as CSS selectors.
+    pub fn css_selectors(&self) -> Vec<(&'static str, String)> {
+        vec![
+            (
+                "make_appointment_button_id",
+                id_css(&self.make_appointment_button_id),
+            ),
+            ("office_item_class", class_css(&self.office_item_class)),
+            ("item_child_class", class_css(&self.item_child_class)),
+            ("active_unit_class", class_css(&self.active_unit_class)),
+            (
+                "available_date_class",
+                class_css(&self.available_date_class),
+            ),
+            ("datepicker_class", class_css(&self.datepicker_class)),
+            ("time_slot_class", class_css(&self.time_slot_class)),
+            ("back_button_id", id_css(&self.back_button_id)),
+            ("next_button_class", class_css(&self.next_button_class)),
+            ("recaptcha_response_id", id_css(&self.recaptcha_response_id)),
+            ("first_name_input_id", id_css(&self.first_name_input_id)),
+            ("last_name_input_id", id_css(&self.last_name_input_id)),
+            ("phone_number_input_id", id_css(&self.phone_number_input_id)),
+            ("email_input_id", id_css(&self.email_input_id)),
+            (
+                "confirm_email_input_id",
+                id_css(&self.confirm_email_input_id),
+            ),
+        ]
+    }
+
+    /// Origin of `base_url`, which geolocation is granted to.
+    pub fn origin(&self) -> String {
+        url::Url::parse(&self.base_url)
+            .map(|url| url.origin().ascii_serialization())
+            .unwrap_or_default()
+    }
+
+    pub fn validate(&self) -> Vec<FieldError> {
+        let mut errors = Vec::new();
+
+        if let Err(e) = url::Url::parse(&self.base_url) {
+            errors.push(FieldError::new(
+                "base_url",
+                format!("is not a valid URL: {}", e),
+            ));
+        }
+
+        for (field, css) in self.css_selectors() {
+            if css.len() <= 1 || Selector::parse(&css).is_err() {
+                errors.push(FieldError::new(field, "is not a valid ID or class name"));
+            }
+        }
+
+        errors
+    }
+}
+
+static HOTFIX: Lazy<RwLock<Option<Arc<SelectorProfile>>>> = Lazy::new(|| RwLock::new(None));
+
+/// The profile scrapers should use: the hotfix if one is set, else the configured one.
+pub fn active(config: &AppConfig) -> Arc<SelectorProfile> {
+    hotfix().unwrap_or_else(|| Arc::new(config.selectors.clone()))
+}
+
+pub fn hotfix() -> Option<Arc<SelectorProfile>> {
+    HOTFIX.read().unwrap().clone()
+}
+
+/// Replaces or, with `None`, clears the hotfix. Sessions pick it up on their next sweep.
+pub fn set_hotfix(profile: Option<SelectorProfile>) {
+    *HOTFIX.write().unwrap() = profile.map(Arc::new);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scraping::parser::parse_offices;
+
+    #[test]
+    fn compiled_in_profile_is_valid() {
+        assert!(SelectorProfile::default().validate().is_empty());
+        assert_eq!(
+            class_css(&SelectorProfile::default().available_date_class),
+            ".ui-state-default.ui-state-active"
+        );
+    }
+
+    #[test]
+    fn invalid_profiles_name_their_fields() {
+        let profile = SelectorProfile {
+            base_url: "not a url".to_string(),
+            office_item_class: String::new(),
+            back_button_id: "1[".to_string(),
+            ..SelectorProfile::default()
+        };
+        let fields: Vec<&str> = profile.validate().iter().map(|e| e.field).collect();
+        assert_eq!(
+            fields,
+            vec!["base_url", "office_item_class", "back_button_id"]
+        );
+    }
+
+    #[test]
+    fn overrides_follow_renamed_markup() {
+        let html = r#"<div class="OfficeRow Open"><div><div>Cary</div></div>
+            <div class="OfficeAddress">1387 SE Maynard Rd, Cary, NC 27511</div><div>13 Miles</div></div>"#;
+        let profile = SelectorProfile {
+            office_item_class: "OfficeRow".to_string(),
+            item_child_class: "OfficeAddress".to_string(),
+            active_unit_class: "Open".to_string(),
+            ..SelectorProfile::default()
+        };
+
+        assert_eq!(
+            parse_offices(html, &SelectorProfile::default()).unwrap(),
+            Vec::new()
+        );
+        let offices = parse_offices(html, &profile).unwrap();
+        assert_eq!(offices.len(), 1);
+        assert_eq!(offices[0].zip_code, "27511");
+        assert!(offices[0].is_reservable);
+    }
+}
@@ -0,0 +1,125 @@
+use std::future::Future;
+use std::time::Duration;
+use tokio::time::Instant;
+
+use crate::scraping::error::{Recovery, ScraperError, ScraperResult};
+
+/// Default time a page gets to reach the expected state.
+pub const PAGE_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Default delay between two checks of a condition.
+pub const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Polls `condition` every `interval` until it yields a value, failing with
+/// `ScraperError::NavigationTimeout` naming `what` once `timeout` has passed.
+///
+/// Errors the scraper would retry, such as a missing element, count as "not yet";
+/// any other error is returned immediately.
+pub async fn wait_for<T, F, Fut>(
+    what: &str,
+    timeout: Duration,
+    interval: Duration,
+    mut condition: F,
+) -> ScraperResult<T>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = ScraperResult<Option<T>>>,
+{
+    let deadline = Instant::now() + timeout;
+    let mut last_error = None;
+
+    loop {
+        match condition().await {
+            Ok(Some(value)) => return Ok(value),
+            Ok(None) => {}
+            Err(e) if e.recovery() == Recovery::Retry => last_error = Some(e),
+            Err(e) => return Err(e),
+        }
+
+        if Instant::now() >= deadline {
+            let mut message = format!("{} after {}s", what, timeout.as_secs());
+            if let Some(e) = last_error {
+                message.push_str(&format!(" (last error: {})", e));
+            }
+            return Err(ScraperError::NavigationTimeout(message));
+        }
+
+        tokio::time::sleep(interval).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+
+    const TIMEOUT: Duration = Duration::from_secs(5);
+
+    #[tokio::test(start_paused = true)]
+    async fn times_out_when_the_condition_never_holds() {
+        let start = Instant::now();
+        let result: ScraperResult<()> =
+            wait_for("the calendar", TIMEOUT, POLL_INTERVAL, || async {
+                Ok(None)
+            })
+            .await;
+
+        assert!(
+            matches!(&result, Err(ScraperError::NavigationTimeout(m)) if m == "the calendar after 5s"),
+            "{:?}",
+            result
+        );
+        assert!(start.elapsed() >= TIMEOUT);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn retryable_errors_are_swallowed_until_the_deadline() {
+        let polls = Cell::new(0);
+        let result: ScraperResult<()> = wait_for("the calendar", TIMEOUT, POLL_INTERVAL, || {
+            polls.set(polls.get() + 1);
+            async { Err(ScraperError::DomDrift("no month".to_string())) }
+        })
+        .await;
+
+        assert!(
+            matches!(&result, Err(ScraperError::NavigationTimeout(m)) if m.contains("no month")),
+            "{:?}",
+            result
+        );
+        assert!(polls.get() > 1);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn retryable_errors_give_way_to_a_value() {
+        let polls = Cell::new(0);
+        let result = wait_for("the calendar", TIMEOUT, POLL_INTERVAL, || {
+            polls.set(polls.get() + 1);
+            let poll = polls.get();
+            async move {
+                match poll {
+                    1 => Err(ScraperError::DomDrift("no month".to_string())),
+                    2 => Ok(None),
+                    _ => Ok(Some(poll)),
+                }
+            }
+        })
+        .await;
+
+        assert_eq!(result.unwrap(), 3);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn other_errors_are_returned_immediately() {
+        let start = Instant::now();
+        let polls = Cell::new(0);
+        let result: ScraperResult<()> = wait_for("the calendar", TIMEOUT, POLL_INTERVAL, || {
+            polls.set(polls.get() + 1);
+            async { Err(ScraperError::PortalOutage("maintenance".to_string())) }
+        })
+        .await;
+
+        assert!(matches!(result, Err(ScraperError::PortalOutage(_))));
+        assert_eq!(polls.get(), 1);
+        assert_eq!(start.elapsed(), Duration::ZERO);
+    }
+}
@@ -0,0 +1,41 @@
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::time::Instant;
+
+use crate::models::appointment::RequestStatus;
+use crate::registry::{LISTENERS, ListenerPhase};
+use crate::scraping::scraper::remove_chrome_profiles;
+use crate::store::{RequestStore, transition_request};
+
+/// Stops every listener after the HTTP server has shut down.
+///
+/// Each scraper is cancelled and given until `timeout` to quit its Chrome session.
+/// Requests that were interrupted go back to pending so they are resumed on the next
+/// start, and any Chrome profiles they left behind are removed.
+pub async fn shutdown(store: Arc<dyn RequestStore>, timeout: Duration) {
+    tracing::info!("Stopping all listeners (deadline {:?})", timeout);
+    let statuses = LISTENERS.stop_all(Instant::now() + timeout).await;
+
+    for status in statuses
+        .iter()
+        .filter(|status| status.phase == ListenerPhase::Stopped)
+    {
+        if let Err(e) = transition_request(
+            store.as_ref(),
+            &status.request_id,
+            RequestStatus::Pending,
+            None,
+        )
+        .await
+        {
+            tracing::error!(
+                "Failed to save state of request {}: {:?}",
+                status.request_id,
+                e
+            );
+        }
+    }
+
+    remove_chrome_profiles();
+    tracing::info!("Stopped {} listeners", statuses.len());
+}
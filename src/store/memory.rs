@@ -0,0 +1,137 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use std::collections::HashMap;
+use std::sync::RwLock;
+
+use crate::models::appointment::{AppointmentRequest, RequestStatus};
+use crate::store::RequestStore;
+
+/// Process-local store for development and tests. Nothing survives a restart.
+#[derive(Default)]
+pub struct MemoryRequestStore {
+    requests: RwLock<HashMap<String, AppointmentRequest>>,
+}
+
+#[async_trait]
+impl RequestStore for MemoryRequestStore {
+    async fn insert(&self, request: &AppointmentRequest) -> Result<()> {
+        let mut requests = self.requests.write().unwrap();
+        if requests.contains_key(&request.request_id) {
+            return Err(anyhow::anyhow!(
+                "Request {} already exists",
+                request.request_id
+            ));
+        }
+        requests.insert(request.request_id.clone(), request.clone());
+        Ok(())
+    }
+
+    async fn update(&self, request: &AppointmentRequest) -> Result<()> {
+        match self.requests.write().unwrap().get_mut(&request.request_id) {
+            Some(stored) => {
+                *stored = request.clone();
+                Ok(())
+            }
+            None => Err(anyhow::anyhow!(
+                "No stored request with ID {}",
+                request.request_id
+            )),
+        }
+    }
+
+    async fn get(&self, request_id: &str) -> Result<Option<AppointmentRequest>> {
+        Ok(self.requests.read().unwrap().get(request_id).cloned())
+    }
+
+    async fn list(&self, statuses: &[RequestStatus]) -> Result<Vec<AppointmentRequest>> {
+        Ok(self
+            .requests
+            .read()
+            .unwrap()
+            .values()
+            .filter(|request| statuses.is_empty() || statuses.contains(&request.status))
+            .cloned()
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(request_id: &str, status: RequestStatus) -> AppointmentRequest {
+        AppointmentRequest {
+            status,
+            ..AppointmentRequest::sample(request_id)
+        }
+    }
+
+    #[tokio::test]
+    async fn requests_are_stored_by_id() {
+        let store = MemoryRequestStore::default();
+        store
+            .insert(&request("req-1", RequestStatus::Pending))
+            .await
+            .unwrap();
+
+        assert!(
+            store
+                .insert(&request("req-1", RequestStatus::Pending))
+                .await
+                .is_err()
+        );
+        assert_eq!(
+            store.get("req-1").await.unwrap().map(|r| r.request_id),
+            Some("req-1".to_string())
+        );
+        assert!(store.get("req-2").await.unwrap().is_none());
+
+        store
+            .update(&request("req-1", RequestStatus::Scanning))
+            .await
+            .unwrap();
+        assert_eq!(
+            store.get("req-1").await.unwrap().map(|r| r.status),
+            Some(RequestStatus::Scanning)
+        );
+        assert!(
+            store
+                .update(&request("req-2", RequestStatus::Scanning))
+                .await
+                .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn list_filters_by_status() {
+        let store = MemoryRequestStore::default();
+        for (id, status) in [
+            ("pending", RequestStatus::Pending),
+            ("scanning", RequestStatus::Scanning),
+            ("booked", RequestStatus::Booked),
+        ] {
+            store.insert(&request(id, status)).await.unwrap();
+        }
+
+        let ids = |requests: Vec<AppointmentRequest>| {
+            let mut ids: Vec<String> = requests.into_iter().map(|r| r.request_id).collect();
+            ids.sort();
+            ids
+        };
+        assert_eq!(
+            ids(store
+                .list(&[RequestStatus::Pending, RequestStatus::Scanning])
+                .await
+                .unwrap()),
+            vec!["pending", "scanning"]
+        );
+        assert_eq!(store.list(&[]).await.unwrap().len(), 3);
+        assert!(
+            store
+                .list(&[RequestStatus::Failed])
+                .await
+                .unwrap()
+                .is_empty()
+        );
+    }
+}
@@ -0,0 +1,124 @@
+pub mod memory;
+pub mod mongo;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use std::sync::Arc;
+
+use crate::config::{AppConfig, StoreBackend};
+use crate::models::appointment::{AppointmentRequest, RequestStatus};
+
+/// Persistence for appointment requests.
+#[async_trait]
+pub trait RequestStore: Send + Sync {
+    async fn insert(&self, request: &AppointmentRequest) -> Result<()>;
+
+    /// Replaces the stored request that has the same `request_id`.
+    async fn update(&self, request: &AppointmentRequest) -> Result<()>;
+
+    async fn get(&self, request_id: &str) -> Result<Option<AppointmentRequest>>;
+
+    /// Lists requests in any of `statuses`, or every request if `statuses` is empty.
+    async fn list(&self, statuses: &[RequestStatus]) -> Result<Vec<AppointmentRequest>>;
+}
+
+/// Builds the store selected by `request_store`.
+pub async fn from_config(config: &AppConfig) -> Result<Arc<dyn RequestStore>> {
+    match (config.store_backend(), &config.mongodb_uri) {
+        (StoreBackend::Mongo, Some(uri)) => {
+            Ok(Arc::new(mongo::MongoRequestStore::connect(uri).await?))
+        }
+        (StoreBackend::Mongo, None) => Err(anyhow::anyhow!(
+            "mongodb_uri must be set for the mongo store"
+        )),
+        (StoreBackend::Memory, _) => Ok(Arc::new(memory::MemoryRequestStore::default())),
+    }
+}
+
+/// Moves a stored request to a new lifecycle status, rejecting transitions the
+/// lifecycle does not allow.
+pub async fn transition_request(
+    store: &dyn RequestStore,
+    request_id: &str,
+    to: RequestStatus,
+    failure_reason: Option<String>,
+) -> Result<()> {
+    let Some(mut request) = store.get(request_id).await? else {
+        return Err(anyhow::anyhow!("No stored request with ID {}", request_id));
+    };
+
+    if request.status == to {
+        return Ok(());
+    }
+    request.transition(to, failure_reason)?;
+
+    store.update(&request).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::memory::MemoryRequestStore;
+
+    #[tokio::test]
+    async fn transitions_are_saved() {
+        let store = MemoryRequestStore::default();
+        store
+            .insert(&AppointmentRequest::sample("req-1"))
+            .await
+            .unwrap();
+
+        transition_request(&store, "req-1", RequestStatus::Scanning, None)
+            .await
+            .unwrap();
+        // Moving to the current status is a no-op rather than an error.
+        transition_request(&store, "req-1", RequestStatus::Scanning, None)
+            .await
+            .unwrap();
+        transition_request(
+            &store,
+            "req-1",
+            RequestStatus::Failed,
+            Some("portal outage".to_string()),
+        )
+        .await
+        .unwrap();
+
+        let stored = store.get("req-1").await.unwrap().unwrap();
+        assert_eq!(stored.status, RequestStatus::Failed);
+        assert_eq!(stored.failure_reason.as_deref(), Some("portal outage"));
+        assert_eq!(stored.status_history.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn illegal_transitions_are_rejected() {
+        let store = MemoryRequestStore::default();
+        store
+            .insert(&AppointmentRequest::sample("req-1"))
+            .await
+            .unwrap();
+
+        assert!(
+            transition_request(&store, "req-1", RequestStatus::Booked, None)
+                .await
+                .is_err()
+        );
+        transition_request(&store, "req-1", RequestStatus::Cancelled, None)
+            .await
+            .unwrap();
+        assert!(
+            transition_request(&store, "req-1", RequestStatus::Scanning, None)
+                .await
+                .is_err()
+        );
+
+        let stored = store.get("req-1").await.unwrap().unwrap();
+        assert_eq!(stored.status, RequestStatus::Cancelled);
+        assert_eq!(stored.status_history.len(), 1);
+        assert!(
+            transition_request(&store, "missing", RequestStatus::Scanning, None)
+                .await
+                .is_err()
+        );
+    }
+}
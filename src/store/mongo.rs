@@ -0,0 +1,100 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use mongodb::bson::{Document, doc, to_bson};
+use mongodb::{Client, Collection, options::ClientOptions};
+
+use crate::models::appointment::{AppointmentRequest, RequestStatus};
+use crate::store::RequestStore;
+
+/// Stores appointment requests in the `InstantDMV.users_nc` collection.
+pub struct MongoRequestStore {
+    collection: Collection<AppointmentRequest>,
+}
+
+impl MongoRequestStore {
+    pub async fn connect(uri: &str) -> Result<Self> {
+        let client_options = ClientOptions::parse(uri).await?;
+        let client = Client::with_options(client_options)?;
+        let db = client.database("InstantDMV");
+        Ok(MongoRequestStore {
+            collection: db.collection::<AppointmentRequest>("users_nc"),
+        })
+    }
+
+    /// Documents written before request IDs existed are given one, and it is saved
+    /// back so later updates can find them.
+    async fn backfill_request_id(&self, request: &mut AppointmentRequest) -> Result<()> {
+        request.request_id = uuid::Uuid::new_v4().to_string();
+        self.collection
+            .update_one(
+                doc! {
+                    "request_id": { "$exists": false },
+                    "email": &request.email,
+                    "service_title": &request.service_title,
+                    "dates": &request.dates,
+                },
+                doc! { "$set": { "request_id": &request.request_id } },
+            )
+            .await?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl RequestStore for MongoRequestStore {
+    async fn insert(&self, request: &AppointmentRequest) -> Result<()> {
+        self.collection.insert_one(request).await?;
+        Ok(())
+    }
+
+    async fn update(&self, request: &AppointmentRequest) -> Result<()> {
+        let result = self
+            .collection
+            .replace_one(doc! { "request_id": &request.request_id }, request)
+            .await?;
+        if result.matched_count == 0 {
+            return Err(anyhow::anyhow!(
+                "No stored request with ID {}",
+                request.request_id
+            ));
+        }
+        Ok(())
+    }
+
+    async fn get(&self, request_id: &str) -> Result<Option<AppointmentRequest>> {
+        Ok(self
+            .collection
+            .find_one(doc! { "request_id": request_id })
+            .await?)
+    }
+
+    async fn list(&self, statuses: &[RequestStatus]) -> Result<Vec<AppointmentRequest>> {
+        let filter = if statuses.is_empty() {
+            Document::new()
+        } else {
+            let includes_pending = statuses.contains(&RequestStatus::Pending);
+            let statuses = to_bson(statuses)?;
+            // Documents written before the lifecycle existed have no status and are pending.
+            if includes_pending {
+                doc! { "$or": [
+                    { "status": { "$in": statuses } },
+                    { "status": { "$exists": false } },
+                ] }
+            } else {
+                doc! { "status": { "$in": statuses } }
+            }
+        };
+
+        let mut cursor = self.collection.find(filter).await?;
+        let mut requests = Vec::new();
+        while cursor.advance().await? {
+            let mut request = cursor.deserialize_current()?;
+            if request.request_id.is_empty() {
+                self.backfill_request_id(&mut request).await?;
+            }
+            requests.push(request);
+        }
+
+        Ok(requests)
+    }
+}